@@ -31,7 +31,10 @@ pub use crate::nips::nip11::RelayInformationDocument;
 pub use crate::nips::nip46::{NostrConnectMessage, NostrConnectMetadata, NostrConnectURI};
 pub use crate::nips::nip53::{Image, LiveEvent, LiveEventHost, LiveEventStatus, Person};
 pub use crate::nips::nip94::FileMetadata;
-pub use crate::types::{Contact, ImageDimensions, Metadata, Timestamp};
+pub use crate::types::{
+    Contact, ImageDimensions, Metadata, MetadataField, MetadataRecord, MetadataValidationError,
+    Timestamp,
+};
 pub use crate::util::{generate_shared_key, JsonValue};
 
 #[derive(Object)]
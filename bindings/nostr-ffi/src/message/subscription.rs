@@ -183,13 +183,15 @@ impl Filter {
 
     pub fn kind(self: Arc<Self>, kind: u64) -> Arc<Self> {
         let mut builder = unwrap_or_clone_arc(self);
-        builder.inner = builder.inner.kind(kind.into());
+        builder.inner = builder.inner.kind(nostr::Kind::from(kind));
         Arc::new(builder)
     }
 
     pub fn kinds(self: Arc<Self>, kinds: Vec<u64>) -> Arc<Self> {
         let mut builder = unwrap_or_clone_arc(self);
-        builder.inner = builder.inner.kinds(kinds.into_iter().map(|k| k.into()));
+        builder.inner = builder
+            .inner
+            .kinds(kinds.into_iter().map(nostr::Kind::from));
         Arc::new(builder)
     }
 
@@ -2,17 +2,54 @@
 // Copyright (c) 2023-2024 Rust Nostr Developers
 // Distributed under the MIT software license
 
+use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::Arc;
 
+use nostr::bech32::{self, FromBase32, Variant};
 use nostr::serde_json::Value;
 use nostr::{JsonUtil, Url};
-use uniffi::Object;
+use uniffi::{Enum, Object, Record};
 
 use crate::error::Result;
 use crate::helper::unwrap_or_clone_arc;
 use crate::JsonValue;
 
+#[derive(Record)]
+pub struct MetadataRecord {
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub about: Option<String>,
+    pub website: Option<String>,
+    pub picture: Option<String>,
+    pub banner: Option<String>,
+    pub nip05: Option<String>,
+    pub lud06: Option<String>,
+    pub lud16: Option<String>,
+    pub custom: HashMap<String, JsonValue>,
+}
+
+/// Standard metadata field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum MetadataField {
+    Name,
+    DisplayName,
+    About,
+    Website,
+    Picture,
+    Banner,
+    Nip05,
+    Lud06,
+    Lud16,
+}
+
+/// Problem found by [`Metadata::validate`]
+#[derive(Record)]
+pub struct MetadataValidationError {
+    pub field: MetadataField,
+    pub error: String,
+}
+
 #[derive(Clone, Object)]
 pub struct Metadata {
     inner: nostr::Metadata,
@@ -48,10 +85,161 @@ impl Metadata {
         })
     }
 
+    /// Compose metadata from all fields at once
+    ///
+    /// Return an error if `website`, `picture` or `banner` aren't valid URLs.
+    #[uniffi::constructor]
+    pub fn from_fields(record: MetadataRecord) -> Result<Self> {
+        let mut inner = nostr::Metadata {
+            name: record.name,
+            display_name: record.display_name,
+            about: record.about,
+            nip05: record.nip05,
+            lud06: record.lud06,
+            lud16: record.lud16,
+            ..Default::default()
+        };
+
+        if let Some(website) = record.website {
+            inner = inner.website(Url::parse(&website)?);
+        }
+
+        if let Some(picture) = record.picture {
+            inner = inner.picture(Url::parse(&picture)?);
+        }
+
+        if let Some(banner) = record.banner {
+            inner = inner.banner(Url::parse(&banner)?);
+        }
+
+        for (key, value) in record.custom.into_iter() {
+            let value: Value = value.try_into()?;
+            inner = inner.custom_field(key, value);
+        }
+
+        Ok(Self { inner })
+    }
+
     pub fn as_json(&self) -> String {
         self.inner.as_json()
     }
 
+    /// Get all fields
+    pub fn as_record(&self) -> Result<MetadataRecord> {
+        let mut custom: HashMap<String, JsonValue> =
+            HashMap::with_capacity(self.inner.custom.len());
+        for (key, value) in self.inner.custom.iter() {
+            custom.insert(key.clone(), value.clone().try_into()?);
+        }
+
+        Ok(MetadataRecord {
+            name: self.inner.name.clone(),
+            display_name: self.inner.display_name.clone(),
+            about: self.inner.about.clone(),
+            website: self.inner.website.clone(),
+            picture: self.inner.picture.clone(),
+            banner: self.inner.banner.clone(),
+            nip05: self.inner.nip05.clone(),
+            lud06: self.inner.lud06.clone(),
+            lud16: self.inner.lud16.clone(),
+            custom,
+        })
+    }
+
+    /// Compare standard and custom fields with another [`Metadata`]
+    pub fn equals(&self, other: Arc<Metadata>) -> bool {
+        self.inner == other.inner
+    }
+
+    /// Validate the standard fields, reporting all the problems at once
+    ///
+    /// Check the `website`, `picture` and `banner` URLs, the `lud06` LNURL
+    /// and the `nip05` and `lud16` identifiers (`name@domain`).
+    pub fn validate(&self) -> Vec<MetadataValidationError> {
+        let mut errors: Vec<MetadataValidationError> = Vec::new();
+
+        let urls = [
+            (MetadataField::Website, &self.inner.website),
+            (MetadataField::Picture, &self.inner.picture),
+            (MetadataField::Banner, &self.inner.banner),
+        ];
+        for (field, value) in urls.into_iter() {
+            if let Some(value) = value {
+                if let Err(e) = Url::parse(value) {
+                    errors.push(MetadataValidationError {
+                        field,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        if let Some(lud06) = &self.inner.lud06 {
+            if let Err(error) = validate_lnurl(lud06) {
+                errors.push(MetadataValidationError {
+                    field: MetadataField::Lud06,
+                    error,
+                });
+            }
+        }
+
+        let identifiers = [
+            (MetadataField::Nip05, &self.inner.nip05),
+            (MetadataField::Lud16, &self.inner.lud16),
+        ];
+        for (field, value) in identifiers.into_iter() {
+            if let Some(value) = value {
+                if let Err(error) = validate_identifier(value) {
+                    errors.push(MetadataValidationError { field, error });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Check if a standard field is set
+    pub fn has_field(&self, field: MetadataField) -> bool {
+        match field {
+            MetadataField::Name => self.inner.name.is_some(),
+            MetadataField::DisplayName => self.inner.display_name.is_some(),
+            MetadataField::About => self.inner.about.is_some(),
+            MetadataField::Website => self.inner.website.is_some(),
+            MetadataField::Picture => self.inner.picture.is_some(),
+            MetadataField::Banner => self.inner.banner.is_some(),
+            MetadataField::Nip05 => self.inner.nip05.is_some(),
+            MetadataField::Lud06 => self.inner.lud06.is_some(),
+            MetadataField::Lud16 => self.inner.lud16.is_some(),
+        }
+    }
+
+    /// Get the keys of the set fields
+    ///
+    /// Standard fields first (JSON keys), followed by the custom ones (sorted).
+    pub fn field_keys(&self) -> Vec<String> {
+        let standard = [
+            ("name", &self.inner.name),
+            ("display_name", &self.inner.display_name),
+            ("about", &self.inner.about),
+            ("website", &self.inner.website),
+            ("picture", &self.inner.picture),
+            ("banner", &self.inner.banner),
+            ("nip05", &self.inner.nip05),
+            ("lud06", &self.inner.lud06),
+            ("lud16", &self.inner.lud16),
+        ];
+        let mut keys: Vec<String> = standard
+            .into_iter()
+            .filter(|(_, value)| value.is_some())
+            .map(|(key, _)| key.to_string())
+            .collect();
+
+        let mut custom: Vec<String> = self.inner.custom.keys().cloned().collect();
+        custom.sort();
+        keys.extend(custom);
+        keys
+    }
+
     pub fn set_name(self: Arc<Self>, name: String) -> Self {
         let mut builder = unwrap_or_clone_arc(self);
         builder.inner = builder.inner.name(name);
@@ -159,3 +347,190 @@ impl Metadata {
         }
     }
 }
+
+fn validate_lnurl(lnurl: &str) -> std::result::Result<(), String> {
+    let (hrp, data, variant) = bech32::decode(lnurl).map_err(|e| format!("invalid LNURL: {e}"))?;
+    if hrp != "lnurl" || variant != Variant::Bech32 {
+        return Err(String::from("invalid LNURL"));
+    }
+
+    // The data must be an URL
+    let url: Vec<u8> = Vec::<u8>::from_base32(&data).map_err(|e| format!("invalid LNURL: {e}"))?;
+    let url: String = String::from_utf8(url).map_err(|e| format!("invalid LNURL: {e}"))?;
+    Url::parse(&url).map_err(|e| format!("invalid LNURL: {e}"))?;
+
+    Ok(())
+}
+
+/// Validate `name@domain` identifier (NIP05 and lightning address)
+fn validate_identifier(identifier: &str) -> std::result::Result<(), String> {
+    match identifier.split_once('@') {
+        Some((name, domain)) if !name.is_empty() && !domain.is_empty() && !domain.contains('@') => {
+            let url = Url::parse(&format!("https://{domain}")).map_err(|e| e.to_string())?;
+
+            // Compare the whole authority (host and port): `domain` must not include a path, a query, etc.
+            let authority: Option<String> = url.host_str().map(|host| match url.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host.to_string(),
+            });
+            if authority.map_or(false, |authority| authority.eq_ignore_ascii_case(domain)) {
+                Ok(())
+            } else {
+                Err(format!("invalid domain: {domain}"))
+            }
+        }
+        _ => Err(String::from("invalid format: expected `name@domain`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_from_fields_round_trip() {
+        let mut custom = HashMap::new();
+        custom.insert(String::from("bot"), JsonValue::Bool { bool: true });
+        let record = MetadataRecord {
+            name: Some(String::from("myname")),
+            display_name: None,
+            about: Some(String::from("Description")),
+            website: Some(String::from("https://example.com/")),
+            picture: Some(String::from("https://example.com/avatar.png")),
+            banner: None,
+            nip05: Some(String::from("username@example.com")),
+            lud06: None,
+            lud16: Some(String::from("pay@yukikishimoto.com")),
+            custom,
+        };
+
+        let metadata = Metadata::from_fields(record).unwrap();
+        assert_eq!(metadata.get_name(), Some(String::from("myname")));
+        assert_eq!(
+            metadata.get_picture(),
+            Some(String::from("https://example.com/avatar.png"))
+        );
+
+        let record = metadata.as_record().unwrap();
+        assert_eq!(record.name, Some(String::from("myname")));
+        assert_eq!(record.website, Some(String::from("https://example.com/")));
+        assert_eq!(record.banner, None);
+        assert!(matches!(
+            record.custom.get("bot"),
+            Some(JsonValue::Bool { bool: true })
+        ));
+
+        let metadata2 = Metadata::from_fields(record).unwrap();
+        assert_eq!(metadata.inner, metadata2.inner);
+    }
+
+    #[test]
+    fn test_metadata_from_fields_invalid_url() {
+        let record = MetadataRecord {
+            name: None,
+            display_name: None,
+            about: None,
+            website: Some(String::from("not a url")),
+            picture: None,
+            banner: None,
+            nip05: None,
+            lud06: None,
+            lud16: None,
+            custom: HashMap::new(),
+        };
+        assert!(Metadata::from_fields(record).is_err());
+    }
+
+    #[test]
+    fn test_metadata_validate() {
+        let metadata = Metadata::from_json(String::from(
+            r#"{"name":"myname","website":"not a url","picture":"also/not/a/url","banner":"https://example.com/banner.png","nip05":"myname@example.com","lud16":"myname@example.com"}"#,
+        ))
+        .unwrap();
+        let errors = metadata.validate();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, MetadataField::Website);
+        assert_eq!(errors[1].field, MetadataField::Picture);
+
+        let metadata = Metadata::from_json(String::from(
+            r#"{"nip05":"example.com","lud06":"lnurl1dp68gurn8ghj7um9wfmxjcm99e3k7mf0v9cxj0m385ekvcenxc6r2c35xvukxefcv5mkvv34x5ekzd3ev56nyd3hxqurzepexejxxepnxscrvwfnv9nxzcn9xq6xyefhvgcxxcmyxymnserxfq5fns"}"#,
+        ))
+        .unwrap();
+        let errors = metadata.validate();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, MetadataField::Nip05);
+    }
+
+    #[test]
+    fn test_validate_lnurl() {
+        let lnurl = "lnurl1dp68gurn8ghj7um9wfmxjcm99e3k7mf0v9cxj0m385ekvcenxc6r2c35xvukxefcv5mkvv34x5ekzd3ev56nyd3hxqurzepexejxxepnxscrvwfnv9nxzcn9xq6xyefhvgcxxcmyxymnserxfq5fns";
+        assert!(validate_lnurl(lnurl).is_ok());
+        assert!(validate_lnurl(&lnurl.to_uppercase()).is_ok());
+
+        // Wrong checksum
+        let lnurl = "lnurl1dp68gurn8ghj7um9wfmxjcm99e3k7mf0v9cxj0m385ekvcenxc6r2c35xvukxefcv5mkvv34x5ekzd3ev56nyd3hxqurzepexejxxepnxscrvwfnv9nxzcn9xq6xyefhvgcxxcmyxymnserxfq5fnq";
+        assert!(validate_lnurl(lnurl).is_err());
+        assert!(validate_lnurl("lnurl1qqqqqq").is_err());
+
+        // Wrong prefix
+        assert!(
+            validate_lnurl("npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_identifier() {
+        assert!(validate_identifier("myname@example.com").is_ok());
+        assert!(validate_identifier("myname@Example.com").is_ok());
+        assert!(validate_identifier("myname@example.com:8080").is_ok());
+
+        assert!(validate_identifier("myname@example.com/path").is_err());
+        assert!(validate_identifier("myname@example.com?query").is_err());
+        assert!(validate_identifier("myname@example.com:port").is_err());
+        assert!(validate_identifier("example.com").is_err());
+    }
+
+    #[test]
+    fn test_metadata_equals() {
+        let a = Arc::new(Metadata::new()).set_name(String::from("myname"));
+        let b = Arc::new(Metadata::new()).set_name(String::from("myname"));
+        assert!(a.equals(Arc::new(b.clone())));
+
+        let b = Arc::new(b)
+            .set_custom_field(String::from("bot"), JsonValue::Bool { bool: true })
+            .unwrap();
+        assert!(!a.equals(Arc::new(b)));
+
+        let c = Arc::new(Metadata::new()).set_name(String::from("other"));
+        assert!(!a.equals(Arc::new(c)));
+    }
+
+    #[test]
+    fn test_metadata_has_field() {
+        let metadata = Arc::new(Metadata::new());
+        assert!(!metadata.has_field(MetadataField::Website));
+        assert!(metadata.field_keys().is_empty());
+
+        let metadata = Arc::new(
+            metadata
+                .set_website(String::from("https://example.com/"))
+                .unwrap(),
+        );
+        assert!(metadata.has_field(MetadataField::Website));
+        assert!(!metadata.has_field(MetadataField::Name));
+
+        let metadata = Arc::new(metadata.set_name(String::from("myname")));
+        let metadata = metadata
+            .set_custom_field(String::from("bot"), JsonValue::Bool { bool: true })
+            .unwrap();
+        assert_eq!(
+            metadata.field_keys(),
+            vec![
+                String::from("name"),
+                String::from("website"),
+                String::from("bot")
+            ]
+        );
+    }
+}
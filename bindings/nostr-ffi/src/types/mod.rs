@@ -9,5 +9,5 @@ mod time;
 
 pub use self::contact::Contact;
 pub use self::image::ImageDimensions;
-pub use self::metadata::Metadata;
+pub use self::metadata::{Metadata, MetadataField, MetadataRecord, MetadataValidationError};
 pub use self::time::Timestamp;
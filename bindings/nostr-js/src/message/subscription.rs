@@ -200,7 +200,7 @@ impl JsFilter {
 
     /// Set author
     pub fn author(self, author: &JsPublicKey) -> Self {
-        self.inner.author(author.into()).into()
+        self.inner.author(author.inner).into()
     }
 
     /// Set authors
@@ -233,7 +233,7 @@ impl JsFilter {
 
     /// Set pubkey
     pub fn pubkey(self, pubkey: &JsPublicKey) -> Self {
-        self.inner.pubkey(pubkey.into()).into()
+        self.inner.pubkey(pubkey.inner).into()
     }
 
     /// Set pubkeys
@@ -279,9 +279,9 @@ impl Client {
     }
 
     pub fn reconcile(&self, filter: Arc<Filter>, opts: Arc<NegentropyOptions>) -> Result<()> {
-        Ok(self
-            .inner
-            .reconcile(filter.as_ref().deref().clone(), **opts)?)
+        self.inner
+            .reconcile(filter.as_ref().deref().clone(), **opts)?;
+        Ok(())
     }
 
     pub fn handle_notifications(self: Arc<Self>, handler: Box<dyn HandleNotification>) {
@@ -62,4 +62,13 @@ impl NegentropyOptions {
         builder.inner = builder.inner.direction(direction.into());
         builder
     }
+
+    /// Dry run (default: false)
+    ///
+    /// If `true`, only compute the reconciliation output without sending or receiving events.
+    pub fn dry_run(self: Arc<Self>, dry_run: bool) -> Self {
+        let mut builder = unwrap_or_clone_arc(self);
+        builder.inner = builder.inner.dry_run(dry_run);
+        builder
+    }
 }
@@ -565,7 +565,8 @@ impl JsClient {
         self.inner
             .reconcile(filter.deref().clone(), **opts)
             .await
-            .map_err(into_err)
+            .map_err(into_err)?;
+        Ok(())
     }
 
     /// Handle notifications
@@ -65,4 +65,12 @@ impl JsNegentropyOptions {
     pub fn direction(self, direction: JsNegentropyDirection) -> Self {
         self.inner.direction(direction.into()).into()
     }
+
+    /// Dry run (default: false)
+    ///
+    /// If `true`, only compute the reconciliation output without sending or receiving events.
+    #[wasm_bindgen(js_name = dryRun)]
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        self.inner.dry_run(dry_run).into()
+    }
 }
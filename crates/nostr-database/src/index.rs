@@ -121,6 +121,7 @@ impl From<[u8; 32]> for PublicKeyPrefix {
 pub struct FilterIndex {
     ids: HashSet<EventId>,
     authors: HashSet<PublicKeyPrefix>,
+    exclude_authors: HashSet<PublicKeyPrefix>,
     kinds: HashSet<Kind>,
     since: Option<Timestamp>,
     until: Option<Timestamp>,
@@ -158,7 +159,8 @@ impl FilterIndex {
     }
 
     fn authors_match(&self, event: &EventIndex) -> bool {
-        self.authors.is_empty() || self.authors.contains(&event.pubkey)
+        (self.authors.is_empty() || self.authors.contains(&event.pubkey))
+            && !self.exclude_authors.contains(&event.pubkey)
     }
 
     fn tag_match(&self, event: &EventIndex) -> bool {
@@ -204,6 +206,11 @@ impl From<Filter> for FilterIndex {
                 .into_iter()
                 .map(PublicKeyPrefix::from)
                 .collect(),
+            exclude_authors: value
+                .exclude_authors
+                .into_iter()
+                .map(PublicKeyPrefix::from)
+                .collect(),
             kinds: value.kinds,
             since: value.since,
             until: value.until,
@@ -309,6 +316,11 @@ impl From<&Filter> for QueryPattern {
         let ids_len = filter.ids.len();
         let generic_tags_len = filter.generic_tags.len();
 
+        // Excluded authors are only checked by the generic query
+        if !filter.exclude_authors.is_empty() {
+            return Self::Generic;
+        }
+
         if kinds_len == 1
             && first_kind.map_or(false, |k| k.is_replaceable())
             && authors_len == 1
@@ -852,7 +864,7 @@ mod tests {
 
     use nostr::secp256k1::schnorr::Signature;
     use nostr::secp256k1::SecretKey;
-    use nostr::{FromBech32, JsonUtil, Keys, Tag};
+    use nostr::{EventBuilder, FromBech32, JsonUtil, Keys, Tag, TagKind};
 
     use super::*;
 
@@ -1165,4 +1177,62 @@ mod tests {
         assert!(!filter.match_event(&event));
         assert!(!filter.match_event(&event_with_empty_tags));
     }
+
+    #[test]
+    fn test_match_event_exclude_authors() {
+        let muted = Keys::generate();
+        let other = Keys::generate();
+
+        let muted_event = EventBuilder::text_note("muted", [])
+            .to_event(&muted)
+            .unwrap();
+        let other_event = EventBuilder::text_note("not muted", [])
+            .to_event(&other)
+            .unwrap();
+
+        let filter = Filter::new()
+            .kind(Kind::TextNote)
+            .exclude_authors([muted.public_key()]);
+        assert_eq!(filter.as_json(), r#"{"kinds":[1]}"#);
+
+        let filter: FilterIndex = filter.into();
+        assert!(!filter.match_event(&EventIndex::from(&muted_event)));
+        assert!(filter.match_event(&EventIndex::from(&other_event)));
+    }
+
+    #[test]
+    fn test_match_event_exact_tag_name() {
+        let pubkey_p = XOnlyPublicKey::from_str(
+            "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a",
+        )
+        .unwrap();
+        let pubkey_proxy = XOnlyPublicKey::from_str(
+            "68d81165918100b7da43fc28f7d1fc12554466e1115886b9e7bb326f65ec4272",
+        )
+        .unwrap();
+
+        let keys = Keys::generate();
+        let event = EventBuilder::new(
+            Kind::TextNote,
+            "test",
+            [
+                Tag::public_key(pubkey_p),
+                Tag::Generic(
+                    TagKind::Custom(String::from("proxy")),
+                    vec![pubkey_proxy.to_string()],
+                ),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+        let event: EventIndex = EventIndex::from(&event);
+
+        // Match (`p` tag)
+        let filter: FilterIndex = Filter::new().pubkey(pubkey_p).into();
+        assert!(filter.match_event(&event));
+
+        // Not match (value only in `proxy` tag)
+        let filter: FilterIndex = Filter::new().pubkey(pubkey_proxy).into();
+        assert!(!filter.match_event(&event));
+    }
 }
@@ -62,12 +62,14 @@ where
     }
 }
 
+/// Get [`SingleLetterTag`] only if the tag name is exactly one char (i.e. `p` but not `proxy`)
 #[inline]
 fn single_char_tagname(tagname: &str) -> Option<SingleLetterTag> {
-    tagname
-        .chars()
-        .next()
-        .and_then(|first| SingleLetterTag::from_char(first).ok())
+    let mut chars = tagname.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => SingleLetterTag::from_char(c).ok(),
+        _ => None,
+    }
 }
 
 #[inline]
@@ -19,7 +19,7 @@ use tokio::sync::broadcast;
 use super::signer::ClientSigner;
 use super::{Error, Options, TryIntoUrl};
 use crate::relay::{pool, Relay, RelayOptions, RelayPoolNotification};
-use crate::{ClientBuilder, NegentropyOptions, RUNTIME};
+use crate::{ClientBuilder, NegentropyOptions, ReconciliationOutput, RUNTIME};
 
 #[derive(Debug, Clone)]
 pub struct Client {
@@ -429,7 +429,11 @@ impl Client {
     }
 
     /// Negentropy reconciliation
-    pub fn reconcile(&self, filter: Filter, opts: NegentropyOptions) -> Result<(), Error> {
+    pub fn reconcile(
+        &self,
+        filter: Filter,
+        opts: NegentropyOptions,
+    ) -> Result<HashMap<Url, ReconciliationOutput>, Error> {
         RUNTIME.block_on(async move { self.client.reconcile(filter, opts).await })
     }
 
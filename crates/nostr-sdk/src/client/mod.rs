@@ -44,7 +44,8 @@ pub use self::signer::{ClientSigner, ClientSignerType};
 pub use self::zapper::{ClientZapper, ZapDetails, ZapEntity};
 use crate::relay::pool::{self, Error as RelayPoolError, RelayPool};
 use crate::relay::{
-    FilterOptions, NegentropyOptions, Relay, RelayOptions, RelayPoolNotification, RelaySendOptions,
+    FilterOptions, NegentropyOptions, ReconciliationOutput, Relay, RelayOptions,
+    RelayPoolNotification, RelaySendOptions,
 };
 use crate::util::TryIntoUrl;
 
@@ -736,6 +737,24 @@ impl Client {
         Ok(self.pool.send_event_to(url, event, opts).await?)
     }
 
+    /// Send event to a subset of the added relays
+    ///
+    /// Return an error if one of the URLs doesn't belong to an added relay.
+    ///
+    /// This method will wait for the `OK` message from the relays.
+    pub async fn send_event_to_relays<I, U>(&self, urls: I, event: Event) -> Result<EventId, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let timeout: Option<Duration> = self.opts.send_timeout;
+        let opts = RelaySendOptions::new()
+            .skip_disconnected(self.opts.get_skip_disconnected_relays())
+            .timeout(timeout);
+        Ok(self.pool.send_event_to_relays(urls, event, opts).await?)
+    }
+
     /// Signs the [`EventBuilder`] into an [`Event`] using the [`ClientSigner`]
     pub async fn sign_event_builder(&self, builder: EventBuilder) -> Result<Event, Error> {
         match self.signer().await? {
@@ -1410,18 +1429,26 @@ impl Client {
 
     /// Negentropy reconciliation
     ///
+    /// Return the [`ReconciliationOutput`] of each relay.
+    ///
     /// <https://github.com/hoytech/negentropy>
-    pub async fn reconcile(&self, filter: Filter, opts: NegentropyOptions) -> Result<(), Error> {
+    pub async fn reconcile(
+        &self,
+        filter: Filter,
+        opts: NegentropyOptions,
+    ) -> Result<HashMap<Url, ReconciliationOutput>, Error> {
         Ok(self.pool.reconcile(filter, opts).await?)
     }
 
     /// Negentropy reconciliation with items
+    ///
+    /// Return the [`ReconciliationOutput`] of each relay.
     pub async fn reconcile_with_items(
         &self,
         filter: Filter,
         items: Vec<(EventId, Timestamp)>,
         opts: NegentropyOptions,
-    ) -> Result<(), Error> {
+    ) -> Result<HashMap<Url, ReconciliationOutput>, Error> {
         Ok(self.pool.reconcile_with_items(filter, items, opts).await?)
     }
 
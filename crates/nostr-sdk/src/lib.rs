@@ -41,8 +41,9 @@ pub use self::client::blocking;
 pub use self::client::{Client, ClientBuilder, ClientSigner, Options};
 pub use self::relay::{
     ActiveSubscription, AtomicRelayServiceFlags, FilterOptions, InternalSubscriptionId,
-    NegentropyDirection, NegentropyOptions, Relay, RelayConnectionStats, RelayOptions,
-    RelayPoolNotification, RelayPoolOptions, RelaySendOptions, RelayServiceFlags, RelayStatus,
+    NegentropyDirection, NegentropyOptions, NegentropyProtocolVersion, ReconciliationOutput, Relay,
+    RelayConnectionStats, RelayOptions, RelayPoolNotification, RelayPoolOptions, RelaySendOptions,
+    RelayServiceFlags, RelayStats, RelayStatus,
 };
 
 #[cfg(feature = "blocking")]
@@ -0,0 +1,293 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Mock transport, used in tests
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use async_utility::thread;
+use nostr::negentropy::{Bytes, Negentropy};
+use nostr::{
+    ClientMessage, Event, EventId, JsonUtil, RelayMessage, SubscriptionId, Timestamp, Url,
+};
+use tokio::sync::{mpsc, Notify};
+
+use super::pool::RelayPool;
+use super::transport::{Error, Transport, TransportMessage, TransportReceiver, TransportSender};
+use super::{Relay, RelayOptions, RelayPoolOptions};
+
+type Replies = Arc<dyn Fn(&ClientMessage) -> Vec<RelayMessage> + Send + Sync>;
+
+/// In-process [`Transport`], replying to the client messages with scripted relay messages
+#[derive(Clone, Default)]
+pub(crate) struct MockTransport {
+    replies: Option<Replies>,
+    unreachable: bool,
+    delay: Duration,
+    hold: Option<(usize, Arc<Notify>)>,
+    sent: Option<Arc<Mutex<Vec<ClientMessage>>>>,
+}
+
+impl fmt::Debug for MockTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockTransport")
+            .field("unreachable", &self.unreachable)
+            .field("delay", &self.delay)
+            .field("hold", &self.hold.as_ref().map(|(index, ..)| index))
+            .field("record", &self.sent.is_some())
+            .finish()
+    }
+}
+
+impl MockTransport {
+    /// New mock transport, ignoring every client message
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reply to every client message with the relay messages returned by `replies`
+    pub fn replies<F>(mut self, replies: F) -> Self
+    where
+        F: Fn(&ClientMessage) -> Vec<RelayMessage> + Send + Sync + 'static,
+    {
+        self.replies = Some(Arc::new(replies));
+        self
+    }
+
+    /// Fail every connection attempt
+    pub fn unreachable(mut self) -> Self {
+        self.unreachable = true;
+        self
+    }
+
+    /// Send the replies after `delay`
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Hold the replies to the `index`-th client message of every connection until `gate` is notified
+    pub fn hold(mut self, index: usize, gate: Arc<Notify>) -> Self {
+        self.hold = Some((index, gate));
+        self
+    }
+
+    /// Record the client messages (see [`MockTransport::sent`])
+    pub fn record(mut self) -> Self {
+        self.sent = Some(Arc::default());
+        self
+    }
+
+    /// Client messages received so far
+    ///
+    /// # Panics
+    ///
+    /// Panics if the recording is not enabled with [`MockTransport::record`]
+    pub fn sent(&self) -> Arc<Mutex<Vec<ClientMessage>>> {
+        self.sent.clone().expect("recording not enabled")
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn connect(
+        &self,
+        _url: &Url,
+        _timeout: Option<Duration>,
+    ) -> Result<(Box<dyn TransportSender>, Box<dyn TransportReceiver>), Error> {
+        if self.unreachable {
+            return Err(Error::backend("unreachable"));
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        Ok((
+            Box::new(MockSender {
+                transport: self.clone(),
+                received: 0,
+                tx,
+            }),
+            Box::new(MockReceiver { rx }),
+        ))
+    }
+}
+
+struct MockSender {
+    transport: MockTransport,
+    received: usize,
+    tx: mpsc::UnboundedSender<TransportMessage>,
+}
+
+#[async_trait]
+impl TransportSender for MockSender {
+    async fn send(&mut self, msg: TransportMessage) -> Result<(), Error> {
+        let msg: ClientMessage = match msg {
+            TransportMessage::Text(json) => match ClientMessage::from_json(json) {
+                Ok(msg) => msg,
+                Err(_) => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        let index: usize = self.received;
+        self.received += 1;
+
+        if let Some(sent) = &self.transport.sent {
+            sent.lock().unwrap().push(msg.clone());
+        }
+
+        let replies: Vec<TransportMessage> = match &self.transport.replies {
+            Some(replies) => replies(&msg)
+                .into_iter()
+                .map(|msg| TransportMessage::Text(msg.as_json()))
+                .collect(),
+            None => return Ok(()),
+        };
+
+        let gate: Option<Arc<Notify>> = match &self.transport.hold {
+            Some((i, gate)) if *i == index => Some(gate.clone()),
+            _ => None,
+        };
+
+        if gate.is_none() && self.transport.delay.is_zero() {
+            for reply in replies.into_iter() {
+                self.tx.send(reply).map_err(Error::backend)?;
+            }
+        } else {
+            let delay: Duration = self.transport.delay;
+            let tx = self.tx.clone();
+            thread::spawn(async move {
+                if let Some(gate) = gate {
+                    gate.notified().await;
+                }
+                thread::sleep(delay).await;
+                for reply in replies.into_iter() {
+                    let _ = tx.send(reply);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+struct MockReceiver {
+    rx: mpsc::UnboundedReceiver<TransportMessage>,
+}
+
+#[async_trait]
+impl TransportReceiver for MockReceiver {
+    async fn recv(&mut self) -> Option<Result<TransportMessage, Error>> {
+        self.rx.recv().await.map(Ok)
+    }
+}
+
+/// Reply to every `REQ` with the `events`, followed by `EOSE` if `eose` is set
+pub(crate) fn events_replies(
+    events: Vec<Event>,
+    eose: bool,
+) -> impl Fn(&ClientMessage) -> Vec<RelayMessage> + Send + Sync + 'static {
+    move |msg| match msg {
+        ClientMessage::Req {
+            subscription_id, ..
+        } => {
+            let mut replies: Vec<RelayMessage> = events
+                .iter()
+                .map(|event| RelayMessage::event(subscription_id.clone(), event.clone()))
+                .collect();
+            if eose {
+                replies.push(RelayMessage::eose(subscription_id.clone()));
+            }
+            replies
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Accept every `EVENT`
+pub(crate) fn ok_replies(msg: &ClientMessage) -> Vec<RelayMessage> {
+    match msg {
+        ClientMessage::Event(event) => vec![RelayMessage::ok(event.id(), true, "")],
+        _ => Vec::new(),
+    }
+}
+
+/// Act as a negentropy relay storing the `items`
+pub(crate) fn negentropy_replies(
+    items: Vec<(EventId, Timestamp)>,
+    frame_size_limit: Option<u64>,
+) -> impl Fn(&ClientMessage) -> Vec<RelayMessage> + Send + Sync + 'static {
+    let negentropy: Mutex<Option<Negentropy>> = Mutex::new(None);
+    move |msg| match msg {
+        ClientMessage::NegOpen {
+            subscription_id,
+            id_size,
+            initial_message,
+            ..
+        } => {
+            let id_size: usize = *id_size as usize;
+            let mut n = Negentropy::new(id_size, frame_size_limit).unwrap();
+            for (id, timestamp) in items.iter() {
+                let id = Bytes::from_slice(&id.as_bytes()[..id_size]);
+                n.add_item(timestamp.as_u64(), id).unwrap();
+            }
+            n.seal().unwrap();
+            let mut negentropy = negentropy.lock().unwrap();
+            vec![negentropy_response(
+                negentropy.insert(n),
+                subscription_id,
+                initial_message,
+            )]
+        }
+        ClientMessage::NegMsg {
+            subscription_id,
+            message,
+        } => match negentropy.lock().unwrap().as_mut() {
+            Some(negentropy) => vec![negentropy_response(negentropy, subscription_id, message)],
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+fn negentropy_response(
+    negentropy: &mut Negentropy,
+    subscription_id: &SubscriptionId,
+    query: &str,
+) -> RelayMessage {
+    let query = Bytes::from_hex(query.to_owned()).unwrap();
+    let response: Bytes = negentropy.reconcile(&query).unwrap();
+    RelayMessage::NegMsg {
+        subscription_id: subscription_id.clone(),
+        message: response.to_hex(),
+    }
+}
+
+/// Negentropy items of the `events`
+pub(crate) fn negentropy_items(events: &[Event]) -> Vec<(EventId, Timestamp)> {
+    events.iter().map(|e| (e.id(), e.created_at())).collect()
+}
+
+/// Relay options using the `transport`, without reconnection
+pub(crate) fn mock_opts(transport: MockTransport) -> RelayOptions {
+    RelayOptions::new().reconnect(false).transport(transport)
+}
+
+/// Pool connected to a single relay using the `transport`
+pub(crate) async fn mock_pool_relay(transport: MockTransport) -> (RelayPool, Relay) {
+    let url = Url::parse("ws://mock.relay").unwrap();
+    let pool = RelayPool::new(RelayPoolOptions::default());
+    pool.add_relay(url.clone(), mock_opts(transport))
+        .await
+        .unwrap();
+    pool.connect(Some(Duration::from_secs(1))).await;
+    let relay = pool.relay(url).await.unwrap();
+    (pool, relay)
+}
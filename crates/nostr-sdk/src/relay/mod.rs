@@ -14,9 +14,8 @@ use std::{cmp, fmt};
 
 #[cfg(not(target_arch = "wasm32"))]
 use async_utility::futures_util::stream::AbortHandle;
-use async_utility::{futures_util, thread, time};
-use async_wsocket::futures_util::{Future, SinkExt, StreamExt};
-use async_wsocket::WsMessage;
+use async_utility::{thread, time};
+use async_wsocket::futures_util::Future;
 use nostr::message::relay::NegentropyErrorCode;
 use nostr::message::MessageHandleError;
 use nostr::negentropy::{self, Bytes, Negentropy};
@@ -34,22 +33,27 @@ use tokio::sync::{broadcast, oneshot, Mutex, RwLock};
 
 mod flags;
 pub mod limits;
+#[cfg(test)]
+mod mock;
 mod options;
 pub mod pool;
 mod stats;
+pub mod transport;
 
 pub use self::flags::{AtomicRelayServiceFlags, RelayServiceFlags};
 pub use self::limits::Limits;
 pub use self::options::{
-    FilterOptions, NegentropyDirection, NegentropyOptions, RelayOptions, RelayPoolOptions,
-    RelaySendOptions,
+    FilterOptions, NegentropyDirection, NegentropyOptions, NegentropyProtocolVersion, RelayOptions,
+    RelayPoolOptions, RelaySendOptions,
 };
 use self::options::{
-    MAX_ADJ_RETRY_SEC, MIN_RETRY_SEC, NEGENTROPY_BATCH_SIZE_DOWN, NEGENTROPY_HIGH_WATER_UP,
-    NEGENTROPY_LOW_WATER_UP,
+    MAX_ADJ_RETRY_SEC, MIN_RETRY_SEC, NEGENTROPY_BATCH_SIZE_DOWN, NEGENTROPY_DEFAULT_ID_SIZE,
+    NEGENTROPY_HIGH_WATER_UP, NEGENTROPY_LOW_WATER_UP, NEGENTROPY_MIN_ID_SIZE,
 };
-pub use self::pool::{RelayPoolMessage, RelayPoolNotification};
-pub use self::stats::RelayConnectionStats;
+pub use self::pool::{EventStream, RelayPoolMessage, RelayPoolNotification};
+pub use self::stats::{RelayConnectionStats, RelayStats};
+use self::transport::TransportMessage;
+pub use self::transport::{Transport, TransportReceiver, TransportSender, WebSocketTransport};
 #[cfg(feature = "blocking")]
 use crate::RUNTIME;
 
@@ -130,6 +134,17 @@ pub enum Error {
     /// Unknown negentropy error
     #[error("unknown negentropy error")]
     UnknownNegentropyError,
+    /// Negentropy ID size out of the `8..=32` range
+    #[error("invalid negentropy ID size: {0} (allowed range: 8..=32)")]
+    InvalidNegentropyIdSize(u8),
+    /// Negentropy protocol version not supported by the relay
+    #[error("negentropy protocol version not supported: requested={requested:#04x}, supported={supported:#04x}")]
+    NegentropyProtocolVersionNotSupported {
+        /// Requested version
+        requested: u8,
+        /// Version supported by the relay
+        supported: u8,
+    },
     /// Relay message too large
     #[error("Received message too large: size={size}, max_size={max_size}")]
     RelayMessageTooLarge {
@@ -154,6 +169,22 @@ pub enum Error {
         /// Max tags num
         max_size: usize,
     },
+    /// Outgoing event has too many tags
+    #[error("Event exceeds the max number of tags: tags={tags}, max_tags={max_tags}")]
+    EventExceedsMaxTags {
+        /// Tags num
+        tags: usize,
+        /// Max tags num
+        max_tags: usize,
+    },
+    /// Outgoing event content too long
+    #[error("Event exceeds the max content length: length={length}, max_length={max_length}")]
+    EventExceedsMaxContentLength {
+        /// Content length
+        length: usize,
+        /// Max content length
+        max_length: usize,
+    },
 }
 
 /// Relay connection status
@@ -288,13 +319,22 @@ impl ActiveSubscription {
     }
 }
 
+/// Negentropy reconciliation output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconciliationOutput {
+    /// IDs of the events that the relay doesn't have (to upload)
+    pub have: HashSet<EventId>,
+    /// IDs of the events that are missing locally (to download)
+    pub need: HashSet<EventId>,
+}
+
 /// Relay
 #[derive(Debug, Clone)]
 pub struct Relay {
     url: Url,
     status: Arc<RwLock<RelayStatus>>,
     #[cfg(feature = "nip11")]
-    document: Arc<RwLock<RelayInformationDocument>>,
+    document: Arc<RwLock<Option<RelayInformationDocument>>>,
     opts: RelayOptions,
     stats: RelayConnectionStats,
     database: Arc<DynNostrDatabase>,
@@ -330,7 +370,7 @@ impl Relay {
             url,
             status: Arc::new(RwLock::new(RelayStatus::Initialized)),
             #[cfg(feature = "nip11")]
-            document: Arc::new(RwLock::new(RelayInformationDocument::new())),
+            document: Arc::new(RwLock::new(None)),
             opts,
             stats: RelayConnectionStats::new(),
             database,
@@ -356,6 +396,19 @@ impl Relay {
         self.opts.proxy
     }
 
+    /// Get [`Transport`]
+    ///
+    /// Return the custom transport set in [`RelayOptions`] or the default [`WebSocketTransport`]
+    fn transport(&self) -> Arc<dyn Transport> {
+        match &self.opts.transport {
+            Some(transport) => transport.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => Arc::new(WebSocketTransport::new().proxy(self.proxy())),
+            #[cfg(target_arch = "wasm32")]
+            None => Arc::new(WebSocketTransport::new()),
+        }
+    }
+
     /// Get [`RelayStatus`]
     pub async fn status(&self) -> RelayStatus {
         let status = self.status.read().await;
@@ -393,8 +446,18 @@ impl Relay {
     }
 
     /// Get [`RelayInformationDocument`]
+    ///
+    /// Return an empty document if not fetched yet.
     #[cfg(feature = "nip11")]
     pub async fn document(&self) -> RelayInformationDocument {
+        self.information().await.unwrap_or_default()
+    }
+
+    /// Get cached [`RelayInformationDocument`]
+    ///
+    /// Return `None` if the document has not been fetched (yet).
+    #[cfg(feature = "nip11")]
+    pub async fn information(&self) -> Option<RelayInformationDocument> {
         let document = self.document.read().await;
         document.clone()
     }
@@ -408,7 +471,23 @@ impl Relay {
     #[cfg(feature = "nip11")]
     async fn set_document(&self, document: RelayInformationDocument) {
         let mut d = self.document.write().await;
-        *d = document;
+        *d = Some(document);
+    }
+
+    #[cfg(feature = "nip11")]
+    async fn fetch_document(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let proxy = self.proxy();
+        #[cfg(target_arch = "wasm32")]
+        let proxy = None;
+        match RelayInformationDocument::get(self.url(), proxy).await {
+            Ok(document) => self.set_document(document).await,
+            Err(e) => tracing::error!(
+                "Impossible to get information document from {}: {}",
+                self.url,
+                e
+            ),
+        };
     }
 
     /// Get [`ActiveSubscription`]
@@ -538,23 +617,9 @@ impl Relay {
                             _ => (),
                         };
 
-                        let retry_sec: u64 = if relay.opts.get_adjust_retry_sec() {
-                            let var: u64 =
-                                relay.stats.attempts().saturating_sub(relay.stats.success()) as u64;
-                            if var >= 3 {
-                                let retry_interval: i64 =
-                                    cmp::min(MIN_RETRY_SEC * (1 + var), MAX_ADJ_RETRY_SEC) as i64;
-                                let jitter: i64 = rand::thread_rng().gen_range(-1..=1);
-                                retry_interval.saturating_add(jitter) as u64
-                            } else {
-                                relay.opts().get_retry_sec()
-                            }
-                        } else {
-                            relay.opts().get_retry_sec()
-                        };
-
-                        tracing::trace!("{} retry time set to {retry_sec} secs", relay.url);
-                        thread::sleep(Duration::from_secs(retry_sec)).await;
+                        let delay: Duration = relay.reconnect_delay();
+                        tracing::trace!("{} retry time set to {delay:?}", relay.url);
+                        thread::sleep(delay).await;
                     }
                 });
             } else if connection_timeout.is_some() {
@@ -566,6 +631,32 @@ impl Relay {
         }
     }
 
+    /// Compute the delay before the next reconnection attempt
+    fn reconnect_delay(&self) -> Duration {
+        let retry_sec: u64 = if self.opts.get_adjust_retry_sec() {
+            let var: u64 = self.stats.attempts().saturating_sub(self.stats.success()) as u64;
+            if var >= 3 {
+                let retry_interval: i64 =
+                    cmp::min(MIN_RETRY_SEC * (1 + var), MAX_ADJ_RETRY_SEC) as i64;
+                let jitter: i64 = rand::thread_rng().gen_range(-1..=1);
+                retry_interval.saturating_add(jitter) as u64
+            } else {
+                self.opts.get_retry_sec()
+            }
+        } else {
+            self.opts.get_retry_sec()
+        };
+
+        let delay = Duration::from_secs(retry_sec);
+        match self.opts.reconnect_jitter {
+            Some(jitter) if !jitter.is_zero() => {
+                let jitter_ms: u64 = jitter.as_millis() as u64;
+                delay + Duration::from_millis(rand::thread_rng().gen_range(0..=jitter_ms))
+            }
+            _ => delay,
+        }
+    }
+
     async fn try_connect(&self, connection_timeout: Option<Duration>) {
         self.stats.new_attempt();
 
@@ -575,26 +666,6 @@ impl Relay {
         self.set_status(RelayStatus::Connecting).await;
         tracing::debug!("Connecting to {}", url);
 
-        // Request `RelayInformationDocument`
-        #[cfg(feature = "nip11")]
-        {
-            let relay = self.clone();
-            thread::spawn(async move {
-                #[cfg(not(target_arch = "wasm32"))]
-                let proxy = relay.proxy();
-                #[cfg(target_arch = "wasm32")]
-                let proxy = None;
-                match RelayInformationDocument::get(relay.url(), proxy).await {
-                    Ok(document) => relay.set_document(document).await,
-                    Err(e) => tracing::error!(
-                        "Impossible to get information document from {}: {}",
-                        relay.url,
-                        e
-                    ),
-                };
-            });
-        }
-
         let timeout: Option<Duration> = if self.stats.attempts() > 1 {
             // Many attempts, use the default timeout
             Some(Duration::from_secs(60))
@@ -602,10 +673,28 @@ impl Relay {
             // First attempt, use external timeout
             connection_timeout
         };
-        #[cfg(not(target_arch = "wasm32"))]
-        let connection = async_wsocket::native::connect(&self.url, self.proxy(), timeout).await;
-        #[cfg(target_arch = "wasm32")]
-        let connection = async_wsocket::wasm::connect(&self.url, timeout).await;
+
+        // Request `RelayInformationDocument`
+        #[cfg(feature = "nip11")]
+        {
+            if self.opts.fetch_nip11_on_connect && self.stats.attempts() == 1 {
+                // First attempt: wait for the document, so the limitations are known before subscribing
+                let fetch_timeout: Duration = timeout.unwrap_or(Duration::from_secs(60));
+                if time::timeout(Some(fetch_timeout), self.fetch_document())
+                    .await
+                    .is_none()
+                {
+                    tracing::warn!("Timeout while getting information document from {url}");
+                }
+            } else {
+                let relay = self.clone();
+                thread::spawn(async move {
+                    relay.fetch_document().await;
+                });
+            }
+        }
+
+        let connection = self.transport().connect(&self.url, timeout).await;
 
         // Connect
         match connection {
@@ -672,7 +761,7 @@ impl Relay {
                                     "Sending {json} to {} (size: {size} bytes)",
                                     relay.url
                                 );
-                                match ws_tx.send(WsMessage::Text(json)).await {
+                                match ws_tx.send(TransportMessage::Text(json)).await {
                                     Ok(_) => {
                                         relay.stats.add_bytes_sent(size);
                                         if let Some(sender) = oneshot_sender {
@@ -710,11 +799,11 @@ impl Relay {
                                     "Sending {len} messages to {} (size: {size} bytes)",
                                     relay.url
                                 );
-                                let msgs = msgs
+                                let msgs: Vec<TransportMessage> = msgs
                                     .into_iter()
-                                    .map(|msg| Ok(WsMessage::Text(msg.as_json())));
-                                let mut stream = futures_util::stream::iter(msgs);
-                                match ws_tx.send_all(&mut stream).await {
+                                    .map(|msg| TransportMessage::Text(msg.as_json()))
+                                    .collect();
+                                match ws_tx.send_batch(msgs).await {
                                     Ok(_) => {
                                         relay.stats.add_bytes_sent(size);
                                         if let Some(sender) = oneshot_sender {
@@ -748,7 +837,7 @@ impl Relay {
                             RelayEvent::Ping { nonce } => {
                                 if relay.opts.flags.has_ping() {
                                     match ws_tx
-                                        .send(WsMessage::Ping(
+                                        .send(TransportMessage::Ping(
                                             nonce.to_string().as_bytes().to_vec(),
                                         ))
                                         .await
@@ -816,7 +905,19 @@ impl Relay {
                         let msg = RawRelayMessage::from_json(&data)?;
                         tracing::trace!("Received message from {}: {:?}", relay.url, msg);
 
+                        // Forget the subscriptions closed by the relay, so they are not resumed
+                        if let RawRelayMessage::Closed {
+                            subscription_id, ..
+                        } = &msg
+                        {
+                            let id = SubscriptionId::new(subscription_id);
+                            let mut subscriptions = relay.subscriptions.write().await;
+                            subscriptions.retain(|_, sub| sub.id != id);
+                        }
+
                         if let RawRelayMessage::Event { event, .. } = &msg {
+                            relay.stats.new_event_received();
+
                             // Check event size
                             let size: usize = event.to_string().as_bytes().len();
                             let max_size: usize = relay.limits.events.max_size as usize;
@@ -856,10 +957,10 @@ impl Relay {
                     }
 
                     #[cfg(not(target_arch = "wasm32"))]
-                    while let Some(msg_res) = ws_rx.next().await {
+                    while let Some(msg_res) = ws_rx.recv().await {
                         if let Ok(msg) = msg_res {
                             match msg {
-                                WsMessage::Pong(bytes) => {
+                                TransportMessage::Pong(bytes) => {
                                     if relay.opts.flags.has_ping() {
                                         match String::from_utf8(bytes) {
                                             Ok(nonce) => match nonce.parse::<u64>() {
@@ -906,18 +1007,20 @@ impl Relay {
                     }
 
                     #[cfg(target_arch = "wasm32")]
-                    while let Some(msg) = ws_rx.next().await {
-                        let data: Vec<u8> = msg.as_ref().to_vec();
-                        match func(&relay, data).await {
-                            Ok(exit) => {
-                                if exit {
-                                    break;
+                    while let Some(msg_res) = ws_rx.recv().await {
+                        if let Ok(msg) = msg_res {
+                            let data: Vec<u8> = msg.into_data();
+                            match func(&relay, data).await {
+                                Ok(exit) => {
+                                    if exit {
+                                        break;
+                                    }
                                 }
+                                Err(e) => tracing::error!(
+                                    "Impossible to handle relay message from {}: {e}",
+                                    relay.url
+                                ),
                             }
-                            Err(e) => tracing::error!(
-                                "Impossible to handle relay message from {}: {e}",
-                                relay.url
-                            ),
                         }
                     }
 
@@ -928,8 +1031,12 @@ impl Relay {
                     }
                 });
 
-                // Subscribe to relay
-                if self.opts.flags.has_read() {
+                // Subscribe to relay (on reconnection, only if enabled)
+                let reconnected: bool = self.stats.success() > 1;
+                if reconnected && !self.opts.resume_subscriptions {
+                    // Not resumed: forget the subscriptions, they are no longer active
+                    self.subscriptions.write().await.clear();
+                } else if self.opts.flags.has_read() {
                     if let Err(e) = self.resubscribe_all(None).await {
                         tracing::error!(
                             "Impossible to subscribe to {}: {}",
@@ -1068,6 +1175,8 @@ impl Relay {
 
     /// Send event and wait for `OK` relay msg
     pub async fn send_event(&self, event: Event, opts: RelaySendOptions) -> Result<EventId, Error> {
+        self.opts.check_event_limits(&event)?;
+
         let id: EventId = event.id();
 
         if opts.skip_disconnected
@@ -1133,6 +1242,10 @@ impl Relay {
             return Err(Error::BatchEventEmpty);
         }
 
+        for event in events.iter() {
+            self.opts.check_event_limits(event)?;
+        }
+
         if opts.skip_disconnected
             && !self.is_connected().await
             && self.stats.attempts() > 1
@@ -1299,10 +1412,12 @@ impl Relay {
             return Err(Error::ReadDisabled);
         }
 
-        let mut subscriptions = self.subscriptions().await;
-        let subscription = subscriptions
-            .remove(&internal_id)
-            .ok_or(Error::InternalIdNotFound)?;
+        let subscription = {
+            let mut subscriptions = self.subscriptions.write().await;
+            subscriptions
+                .remove(&internal_id)
+                .ok_or(Error::InternalIdNotFound)?
+        };
         self.send_msg(ClientMessage::close(subscription.id), wait)
             .await?;
         Ok(())
@@ -1314,7 +1429,11 @@ impl Relay {
             return Err(Error::ReadDisabled);
         }
 
-        let subscriptions = self.subscriptions().await;
+        // Forget the subscriptions, so they are not resumed on reconnection
+        let subscriptions = {
+            let mut subscriptions = self.subscriptions.write().await;
+            std::mem::take(&mut *subscriptions)
+        };
 
         for sub in subscriptions.into_values() {
             self.send_msg(ClientMessage::close(sub.id.clone()), wait)
@@ -1565,12 +1684,41 @@ impl Relay {
     }
 
     /// Negentropy reconciliation
+    ///
+    /// If [`NegentropyOptions::dry_run`] is enabled, the events are not uploaded/downloaded
+    /// and only the [`ReconciliationOutput`] is computed (with both sets, whatever the direction).
     pub async fn reconcile(
         &self,
         filter: Filter,
         items: Vec<(EventId, Timestamp)>,
         opts: NegentropyOptions,
-    ) -> Result<(), Error> {
+    ) -> Result<ReconciliationOutput, Error> {
+        self.reconcile_internal(filter, items, opts, None).await
+    }
+
+    /// Negentropy reconciliation, streaming the `need` IDs
+    ///
+    /// Every batch of newly discovered `need` IDs is sent to `need_ids` as soon as it's available,
+    /// so the missing events can be fetched before the end of the reconciliation.
+    /// The returned [`ReconciliationOutput`] still contains the full sets.
+    pub async fn reconcile_streaming(
+        &self,
+        filter: Filter,
+        items: Vec<(EventId, Timestamp)>,
+        opts: NegentropyOptions,
+        need_ids: mpsc::UnboundedSender<Vec<EventId>>,
+    ) -> Result<ReconciliationOutput, Error> {
+        self.reconcile_internal(filter, items, opts, Some(need_ids))
+            .await
+    }
+
+    async fn reconcile_internal(
+        &self,
+        filter: Filter,
+        items: Vec<(EventId, Timestamp)>,
+        opts: NegentropyOptions,
+        need_ids_sender: Option<mpsc::UnboundedSender<Vec<EventId>>>,
+    ) -> Result<ReconciliationOutput, Error> {
         // Check if read option is disabled
         if !self.opts.flags.has_read() {
             return Err(Error::ReadDisabled);
@@ -1584,23 +1732,44 @@ impl Relay {
             return Err(Error::NotConnected);
         }
 
+        // Check ID size
+        if !(NEGENTROPY_MIN_ID_SIZE..=NEGENTROPY_DEFAULT_ID_SIZE).contains(&opts.id_size) {
+            return Err(Error::InvalidNegentropyIdSize(opts.id_size));
+        }
+
         // Compose negentropy struct, add items and seal
-        let mut negentropy = Negentropy::new(32, Some(20_000))?;
+        let id_size: usize = opts.id_size as usize;
+        let truncated: bool = id_size < NEGENTROPY_DEFAULT_ID_SIZE as usize;
+        let mut negentropy = Negentropy::new(id_size, Some(20_000))?;
+        let mut local_ids: HashMap<Vec<u8>, EventId> = HashMap::new();
         for (id, timestamp) in items.into_iter() {
-            let id = Bytes::from_slice(id.as_bytes());
-            negentropy.add_item(timestamp.as_u64(), id)?;
+            let prefix: &[u8] = &id.as_bytes()[..id_size];
+            if truncated {
+                local_ids.insert(prefix.to_vec(), id);
+            }
+            negentropy.add_item(timestamp.as_u64(), Bytes::from_slice(prefix))?;
         }
         negentropy.seal()?;
 
+        // Match the IDs back to the full ones: the truncated ones are resolved against the local items.
+        // The truncated IDs of the relay events (not in the local items) can't be resolved.
+        let resolve_id = |id: &[u8]| -> Option<EventId> {
+            if truncated {
+                local_ids.get(id).copied()
+            } else {
+                EventId::from_slice(id).ok()
+            }
+        };
+
+        let mut notifications = self.notification_sender.subscribe();
+        let mut temp_notifications = self.notification_sender.subscribe();
+
         // Send initial negentropy message
         let sub_id = SubscriptionId::generate();
         let open_msg = ClientMessage::neg_open(&mut negentropy, &sub_id, filter)?;
         self.send_msg(open_msg, Some(Duration::from_secs(10)))
             .await?;
 
-        let mut notifications = self.notification_sender.subscribe();
-        let mut temp_notifications = self.notification_sender.subscribe();
-
         // Check if negentropy it's supported
         time::timeout(Some(opts.initial_timeout), async {
             while let Ok(notification) = temp_notifications.recv().await {
@@ -1608,10 +1777,24 @@ impl Relay {
                     if relay_url == self.url {
                         match message {
                             RelayMessage::NegMsg {
-                                subscription_id, ..
+                                subscription_id,
+                                message,
                             } => {
                                 if subscription_id == sub_id {
-                                    break;
+                                    // The first byte is the protocol version supported by the relay
+                                    let requested: u8 = opts.protocol_version.as_u8();
+                                    let supported: Option<u8> = message
+                                        .get(..2)
+                                        .and_then(|v| u8::from_str_radix(v, 16).ok());
+                                    return match supported {
+                                        Some(supported) if supported != requested => {
+                                            Err(Error::NegentropyProtocolVersionNotSupported {
+                                                requested,
+                                                supported,
+                                            })
+                                        }
+                                        _ => Ok(()),
+                                    };
                                 }
                             }
                             RelayMessage::NegErr {
@@ -1653,6 +1836,7 @@ impl Relay {
         let mut sync_done = false;
         let mut have_ids: Vec<Bytes> = Vec::new();
         let mut need_ids: Vec<Bytes> = Vec::new();
+        let mut output: ReconciliationOutput = ReconciliationOutput::default();
         let down_sub_id: SubscriptionId = SubscriptionId::generate();
 
         // Start reconciliation
@@ -1673,11 +1857,29 @@ impl Relay {
                                         &mut need_ids,
                                     )?;
 
-                                    if !do_up {
+                                    // A dry run always reports both sets
+                                    if do_up || opts.dry_run {
+                                        extend_new_ids(&mut output.have, &have_ids, &resolve_id);
+                                    }
+
+                                    if do_down || opts.dry_run {
+                                        let new_ids: Vec<EventId> = extend_new_ids(
+                                            &mut output.need,
+                                            &need_ids,
+                                            &resolve_id,
+                                        );
+                                        if let Some(sender) = &need_ids_sender {
+                                            if !new_ids.is_empty() {
+                                                let _ = sender.send(new_ids);
+                                            }
+                                        }
+                                    }
+
+                                    if !do_up || opts.dry_run {
                                         have_ids.clear();
                                     }
 
-                                    if !do_down {
+                                    if !do_down || opts.dry_run {
                                         need_ids.clear();
                                     }
 
@@ -1739,7 +1941,7 @@ impl Relay {
                                 && in_flight_up.len() < NEGENTROPY_HIGH_WATER_UP
                             {
                                 if let Some(id) = have_ids.pop() {
-                                    if let Ok(event_id) = EventId::from_slice(&id) {
+                                    if let Some(event_id) = resolve_id(&id) {
                                         match self.database.event_by_id(event_id).await {
                                             Ok(event) => {
                                                 in_flight_up.insert(event_id);
@@ -1768,26 +1970,33 @@ impl Relay {
 
                             while !need_ids.is_empty() && ids.len() < NEGENTROPY_BATCH_SIZE_DOWN {
                                 if let Some(id) = need_ids.pop() {
-                                    if let Ok(event_id) = EventId::from_slice(&id) {
-                                        ids.push(event_id);
+                                    match resolve_id(&id) {
+                                        Some(event_id) => ids.push(event_id),
+                                        None => tracing::warn!(
+                                            "Can't download event with truncated ID {}",
+                                            id.to_hex()
+                                        ),
                                     }
                                 }
                             }
 
-                            tracing::info!(
-                                "Negentropy DOWN: {} events ({} remaining)",
-                                ids.len(),
-                                need_ids.len()
-                            );
+                            // An empty `ids` filter would match every event
+                            if !ids.is_empty() {
+                                tracing::info!(
+                                    "Negentropy DOWN: {} events ({} remaining)",
+                                    ids.len(),
+                                    need_ids.len()
+                                );
 
-                            let filter = Filter::new().ids(ids);
-                            self.send_msg(
-                                ClientMessage::req(down_sub_id.clone(), vec![filter]),
-                                None,
-                            )
-                            .await?;
+                                let filter = Filter::new().ids(ids);
+                                self.send_msg(
+                                    ClientMessage::req(down_sub_id.clone(), vec![filter]),
+                                    None,
+                                )
+                                .await?;
 
-                            in_flight_down = true
+                                in_flight_down = true
+                            }
                         }
                     }
                 }
@@ -1818,24 +2027,602 @@ impl Relay {
         };
         self.send_msg(close_msg, None).await?;
 
-        Ok(())
+        Ok(output)
     }
 
     /// Check if relay support negentropy protocol
     pub async fn support_negentropy(&self) -> Result<bool, Error> {
+        self.support_negentropy_with_opts(
+            NegentropyOptions::new().initial_timeout(Duration::from_secs(5)),
+        )
+        .await
+    }
+
+    /// Check if relay support negentropy protocol, using the ID size selected in [`NegentropyOptions`]
+    pub async fn support_negentropy_with_opts(
+        &self,
+        opts: NegentropyOptions,
+    ) -> Result<bool, Error> {
         let pk = Keys::generate();
         let filter = Filter::new().author(pk.public_key());
-        match self
-            .reconcile(
-                filter,
-                Vec::new(),
-                NegentropyOptions::new().initial_timeout(Duration::from_secs(5)),
-            )
-            .await
-        {
+        match self.reconcile(filter, Vec::new(), opts).await {
             Ok(_) => Ok(true),
             Err(Error::NegentropyNotSupported) => Ok(false),
             Err(e) => Err(e),
         }
     }
 }
+
+/// Insert the IDs into the set and return the ones that were not already there
+///
+/// IDs that can't be resolved to an [`EventId`] are skipped.
+fn extend_new_ids<F>(set: &mut HashSet<EventId>, ids: &[Bytes], resolve_id: F) -> Vec<EventId>
+where
+    F: Fn(&[u8]) -> Option<EventId>,
+{
+    ids.iter()
+        .filter_map(|id| resolve_id(id))
+        .filter(|id| set.insert(*id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::serde_json::Value;
+    use nostr::{EventBuilder, Kind};
+    use nostr_database::{DatabaseOptions, IntoNostrDatabase, MemoryDatabase};
+    use tokio::sync::Notify;
+
+    use super::mock::{self, MockTransport};
+    use super::pool::RelayPool;
+    use super::*;
+
+    fn mock_relay(url: Url, opts: RelayOptions) -> (Relay, Receiver<RelayPoolMessage>) {
+        let (pool_sender, pool_receiver) = mpsc::channel(1024);
+        let (notification_sender, _) = broadcast::channel(1024);
+        let relay = Relay::new(
+            url,
+            MemoryDatabase::default().into_nostr_database(),
+            pool_sender,
+            notification_sender,
+            opts,
+            Limits::default(),
+        );
+        (relay, pool_receiver)
+    }
+
+    #[test]
+    fn test_reconnect_delay_jitter() {
+        let url = Url::parse("ws://localhost:8888").unwrap();
+        let jitter = Duration::from_secs(3);
+        let (relay, _) = mock_relay(
+            url.clone(),
+            RelayOptions::new()
+                .retry_sec(10)
+                .adjust_retry_sec(false)
+                .reconnect_jitter(Some(jitter)),
+        );
+
+        let min = Duration::from_secs(10);
+        let max = min + jitter;
+        for _ in 0..100 {
+            let delay = relay.reconnect_delay();
+            assert!(delay >= min && delay <= max, "{delay:?}");
+        }
+
+        // No jitter by default
+        let (relay, _) = mock_relay(url, RelayOptions::new().retry_sec(10));
+        assert_eq!(relay.reconnect_delay(), min);
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport_subscribe_eose() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello from mock transport", [])
+            .to_event(&keys)
+            .unwrap();
+
+        let transport =
+            MockTransport::new().replies(mock::events_replies(vec![event.clone()], true));
+        let (relay, mut pool_receiver) = mock_relay(
+            Url::parse("ws://mock.relay").unwrap(),
+            mock::mock_opts(transport),
+        );
+
+        relay.connect(Some(Duration::from_secs(1))).await;
+        assert!(relay.is_connected().await);
+
+        relay
+            .subscribe(vec![Filter::new().author(keys.public_key())], None)
+            .await
+            .unwrap();
+
+        let mut received: Option<Value> = None;
+        let eose_received = time::timeout(Some(Duration::from_secs(5)), async {
+            while let Some(msg) = pool_receiver.recv().await {
+                if let RelayPoolMessage::ReceivedMsg { msg, .. } = msg {
+                    match msg {
+                        RawRelayMessage::Event { event, .. } => received = Some(event),
+                        RawRelayMessage::EndOfStoredEvents(..) => return true,
+                        _ => (),
+                    }
+                }
+            }
+            false
+        })
+        .await
+        .unwrap_or(false);
+
+        assert!(eose_received);
+        let received: Value = received.unwrap();
+        assert_eq!(received["id"], Value::String(event.id().to_hex()));
+    }
+
+    /// Reply to every `REQ`, after a small delay so the subscriber is already listening
+    fn reply_to_reqs(reply: fn(SubscriptionId) -> RelayMessage) -> MockTransport {
+        MockTransport::new()
+            .replies(move |msg| match msg {
+                ClientMessage::Req {
+                    subscription_id, ..
+                } => vec![reply(subscription_id.clone())],
+                _ => Vec::new(),
+            })
+            .delay(Duration::from_millis(50))
+            .record()
+    }
+
+    fn sent_reqs(sent: &std::sync::Mutex<Vec<ClientMessage>>) -> Vec<SubscriptionId> {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .filter_map(|msg| match msg {
+                ClientMessage::Req {
+                    subscription_id, ..
+                } => Some(subscription_id.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    async fn wait_for_reqs(sent: &std::sync::Mutex<Vec<ClientMessage>>, num: usize) -> bool {
+        time::timeout(Some(Duration::from_secs(5)), async {
+            while sent_reqs(sent).len() < num {
+                thread::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .is_some()
+    }
+
+    async fn reconnect(relay: &Relay) {
+        relay.stop().await.unwrap();
+        time::timeout(Some(Duration::from_secs(5)), async {
+            while relay.status().await != RelayStatus::Stopped {
+                thread::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+        relay.connect(Some(Duration::from_secs(1))).await;
+        assert!(relay.is_connected().await);
+    }
+
+    fn recording_relay(
+        resume_subscriptions: bool,
+    ) -> (Relay, Arc<std::sync::Mutex<Vec<ClientMessage>>>) {
+        let transport = MockTransport::new().record();
+        let sent = transport.sent();
+        let opts = mock::mock_opts(transport).resume_subscriptions(resume_subscriptions);
+        let (relay, _) = mock_relay(Url::parse("ws://mock.relay").unwrap(), opts);
+        (relay, sent)
+    }
+
+    #[tokio::test]
+    async fn test_resume_subscriptions_after_reconnect() {
+        let (relay, sent) = recording_relay(true);
+        relay.connect(Some(Duration::from_secs(1))).await;
+        relay
+            .subscribe(vec![Filter::new().kind(Kind::TextNote)], None)
+            .await
+            .unwrap();
+        assert!(wait_for_reqs(&sent, 1).await);
+
+        reconnect(&relay).await;
+
+        // Same subscription ID
+        assert!(wait_for_reqs(&sent, 2).await);
+        let reqs = sent_reqs(&sent);
+        assert_eq!(reqs[0], reqs[1]);
+    }
+
+    #[tokio::test]
+    async fn test_not_resume_subscriptions() {
+        // Disabled
+        let (relay, sent) = recording_relay(false);
+        relay.connect(Some(Duration::from_secs(1))).await;
+        relay
+            .subscribe(vec![Filter::new().kind(Kind::TextNote)], None)
+            .await
+            .unwrap();
+        assert!(wait_for_reqs(&sent, 1).await);
+        reconnect(&relay).await;
+        thread::sleep(Duration::from_millis(500)).await;
+        assert_eq!(sent_reqs(&sent).len(), 1);
+        assert!(relay.subscriptions().await.is_empty());
+
+        // Unsubscribed
+        let (relay, sent) = recording_relay(true);
+        relay.connect(Some(Duration::from_secs(1))).await;
+        relay
+            .subscribe(vec![Filter::new().kind(Kind::TextNote)], None)
+            .await
+            .unwrap();
+        assert!(wait_for_reqs(&sent, 1).await);
+        relay.unsubscribe(None).await.unwrap();
+        reconnect(&relay).await;
+        thread::sleep(Duration::from_millis(500)).await;
+        assert_eq!(sent_reqs(&sent).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_not_resume_completed_subscriptions() {
+        let transport = reply_to_reqs(RelayMessage::eose);
+        let sent = transport.sent();
+        let (_pool, relay) = mock::mock_pool_relay(transport).await;
+
+        let filters = vec![Filter::new().kind(Kind::TextNote)];
+        relay.subscribe(filters.clone(), None).await.unwrap();
+
+        // One-shot query, completed by the EOSE
+        relay
+            .get_events_of(filters, Duration::from_secs(5), FilterOptions::ExitOnEOSE)
+            .await
+            .unwrap();
+        assert!(wait_for_reqs(&sent, 2).await);
+
+        reconnect(&relay).await;
+
+        // Only the persistent subscription is resumed
+        assert!(wait_for_reqs(&sent, 3).await);
+        thread::sleep(Duration::from_millis(500)).await;
+        let reqs = sent_reqs(&sent);
+        assert_eq!(reqs.len(), 3);
+        assert_ne!(reqs[1], reqs[0]);
+        assert_eq!(reqs[2], reqs[0]);
+    }
+
+    #[tokio::test]
+    async fn test_forget_closed_subscriptions() {
+        let transport = reply_to_reqs(|id| RelayMessage::closed(id, "error: shutting down"));
+        let sent = transport.sent();
+        let (_pool, relay) = mock::mock_pool_relay(transport).await;
+
+        relay
+            .subscribe(vec![Filter::new().kind(Kind::TextNote)], None)
+            .await
+            .unwrap();
+        assert!(wait_for_reqs(&sent, 1).await);
+
+        // Closed by the relay
+        let forgotten = time::timeout(Some(Duration::from_secs(5)), async {
+            while !relay.subscriptions().await.is_empty() {
+                thread::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        assert!(forgotten.is_some());
+
+        reconnect(&relay).await;
+        thread::sleep(Duration::from_millis(500)).await;
+        assert_eq!(sent_reqs(&sent).len(), 1);
+    }
+
+    /// Serve the NIP-11 document only to requests with the `application/nostr+json` Accept header
+    #[cfg(feature = "nip11")]
+    fn spawn_nip11_server(document: &'static str) -> Url {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0u8; 4096];
+                let len: usize = stream.read(&mut buf).unwrap_or_default();
+                let req = String::from_utf8_lossy(&buf[..len]).to_lowercase();
+                let response: String = if req.contains("accept: application/nostr+json") {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/nostr+json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{document}",
+                        document.len()
+                    )
+                } else {
+                    String::from("HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        Url::parse(&format!("ws://{addr}")).unwrap()
+    }
+
+    #[cfg(feature = "nip11")]
+    #[tokio::test]
+    async fn test_fetch_nip11_on_connect() {
+        let url = spawn_nip11_server(
+            r#"{"name":"mock","supported_nips":[1,11,45],"limitation":{"max_limit":500}}"#,
+        );
+
+        let opts = mock::mock_opts(MockTransport::new()).fetch_nip11_on_connect(true);
+        let (relay, _) = mock_relay(url, opts);
+
+        assert!(relay.information().await.is_none());
+
+        relay.connect(Some(Duration::from_secs(1))).await;
+
+        let document = relay.information().await.unwrap();
+        assert_eq!(document.supported_nips, Some(vec![1, 11, 45]));
+        assert_eq!(document.limitation.and_then(|l| l.max_limit), Some(500));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_truncated_ids() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..3)
+            .map(|i| {
+                EventBuilder::text_note(format!("Event #{i}"), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        // Local: events 0 and 1, relay: events 1 and 2
+        let transport = MockTransport::new().replies(mock::negentropy_replies(
+            mock::negentropy_items(&events[1..]),
+            None,
+        ));
+        let (_pool, relay) = mock::mock_pool_relay(transport).await;
+        let opts = NegentropyOptions::new()
+            .id_size(16)
+            .direction(NegentropyDirection::Both)
+            .dry_run(true);
+        let output = time::timeout(
+            Some(Duration::from_secs(5)),
+            relay.reconcile(Filter::new(), mock::negentropy_items(&events[..2]), opts),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        // The truncated IDs of the local events are matched back to the full ones,
+        // while the relay ones can't be resolved.
+        assert_eq!(output.have, HashSet::from([events[0].id()]));
+        assert!(output.need.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_truncated_ids_upload() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..3)
+            .map(|i| {
+                EventBuilder::text_note(format!("Event #{i}"), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        // Local: events 0 and 1, relay: events 1 and 2
+        let pool = RelayPool::with_database(
+            RelayPoolOptions::default(),
+            MemoryDatabase::new(DatabaseOptions { events: true }),
+        );
+        for event in events[..2].iter() {
+            pool.database().save_event(event).await.unwrap();
+        }
+        let neg_replies = mock::negentropy_replies(mock::negentropy_items(&events[1..]), None);
+        let transport = MockTransport::new()
+            .replies(move |msg| {
+                let mut replies = neg_replies(msg);
+                replies.extend(mock::ok_replies(msg));
+                replies
+            })
+            .record();
+        let sent = transport.sent();
+        let url = Url::parse("ws://mock.relay").unwrap();
+        pool.add_relay(url.clone(), mock::mock_opts(transport))
+            .await
+            .unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+        let relay = pool.relay(url).await.unwrap();
+
+        let opts = NegentropyOptions::new()
+            .id_size(16)
+            .direction(NegentropyDirection::Up);
+        let output = time::timeout(
+            Some(Duration::from_secs(5)),
+            relay.reconcile(Filter::new(), mock::negentropy_items(&events[..2]), opts),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(output.have, HashSet::from([events[0].id()]));
+
+        // The truncated ID is resolved to the full one and the event uploaded
+        let sent = sent.lock().unwrap();
+        assert!(sent
+            .iter()
+            .any(|msg| matches!(msg, ClientMessage::NegOpen { id_size: 16, .. })));
+        let uploaded: Vec<EventId> = sent
+            .iter()
+            .filter_map(|msg| match msg {
+                ClientMessage::Event(event) => Some(event.id()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(uploaded, vec![events[0].id()]);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_invalid_id_size() {
+        let transport = MockTransport::new().record();
+        let sent = transport.sent();
+        let (_pool, relay) = mock::mock_pool_relay(transport).await;
+
+        for id_size in [0, 4, 33, 64] {
+            let opts = NegentropyOptions::new().id_size(id_size);
+            let res = relay.reconcile(Filter::new(), Vec::new(), opts).await;
+            assert!(matches!(res, Err(Error::InvalidNegentropyIdSize(size)) if size == id_size));
+        }
+
+        // Nothing sent to the relay
+        assert!(sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_protocol_version_not_supported() {
+        // Relay replying with a (future) protocol version
+        let transport = MockTransport::new().replies(|msg| match msg {
+            ClientMessage::NegOpen {
+                subscription_id, ..
+            } => vec![RelayMessage::NegMsg {
+                subscription_id: subscription_id.clone(),
+                message: String::from("62"),
+            }],
+            _ => Vec::new(),
+        });
+        let (_pool, relay) = mock::mock_pool_relay(transport).await;
+
+        let res = relay
+            .reconcile(Filter::new(), Vec::new(), NegentropyOptions::new())
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::NegentropyProtocolVersionNotSupported {
+                requested: 0x61,
+                supported: 0x62
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_dry_run_both_sets() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..3)
+            .map(|i| {
+                EventBuilder::text_note(format!("Event #{i}"), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        // Local: events 0 and 1, relay: events 1 and 2
+        let transport = MockTransport::new().replies(mock::negentropy_replies(
+            mock::negentropy_items(&events[1..]),
+            None,
+        ));
+        let (_pool, relay) = mock::mock_pool_relay(transport).await;
+
+        // Upload only, but the `need` set is reported too
+        let opts = NegentropyOptions::new()
+            .direction(NegentropyDirection::Up)
+            .dry_run(true);
+        let output = time::timeout(
+            Some(Duration::from_secs(5)),
+            relay.reconcile(Filter::new(), mock::negentropy_items(&events[..2]), opts),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(output.have, HashSet::from([events[0].id()]));
+        assert_eq!(output.need, HashSet::from([events[2].id()]));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_streaming() {
+        // Enough IDs to exceed the relay frame size limit, so the reconciliation needs many rounds
+        let items: Vec<(EventId, Timestamp)> = (0..300u64)
+            .map(|i| {
+                let mut id = [0u8; 32];
+                id[..8].copy_from_slice(&i.to_be_bytes());
+                (EventId::from_slice(&id).unwrap(), Timestamp::from(i))
+            })
+            .collect();
+
+        // The relay holds the reply to the first `NEG-MSG` (after the `NEG-OPEN`)
+        let gate = Arc::new(Notify::new());
+        let transport = MockTransport::new()
+            .replies(mock::negentropy_replies(items.clone(), Some(4096)))
+            .hold(1, gate.clone())
+            .record();
+        let sent = transport.sent();
+        let (_pool, relay) = mock::mock_pool_relay(transport).await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let opts = NegentropyOptions::new()
+            .direction(NegentropyDirection::Down)
+            .dry_run(true);
+        let handle = thread::spawn(async move {
+            relay
+                .reconcile_streaming(Filter::new(), Vec::new(), opts, tx)
+                .await
+        })
+        .unwrap();
+
+        // The relay holds the second round until the first IDs are received
+        let first: Vec<EventId> = time::timeout(Some(Duration::from_secs(5)), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!first.is_empty());
+        assert!(first.len() < items.len());
+        gate.notify_one();
+
+        let output = time::timeout(Some(Duration::from_secs(5)), handle.join())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        let mut streamed: HashSet<EventId> = first.into_iter().collect();
+        while let Ok(ids) = rx.try_recv() {
+            streamed.extend(ids);
+        }
+        let expected: HashSet<EventId> = items.into_iter().map(|(id, _)| id).collect();
+        assert_eq!(streamed, expected);
+        assert_eq!(output.need, expected);
+
+        let rounds: usize = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| matches!(msg, ClientMessage::NegMsg { .. }))
+            .count();
+        assert!(rounds >= 2);
+    }
+
+    #[test]
+    fn test_extend_new_ids_batches() {
+        let id1 = EventId::all_zeros();
+        let id2 = EventId::from_slice(&[1u8; 32]).unwrap();
+        let id3 = EventId::from_slice(&[2u8; 32]).unwrap();
+
+        let mut need: HashSet<EventId> = HashSet::new();
+        let mut batches: Vec<Vec<EventId>> = Vec::new();
+
+        // Simulate multiple rounds, with IDs not yet downloaded from the previous round
+        let rounds: Vec<Vec<EventId>> = vec![vec![id1, id2], vec![id2, id3], vec![id3]];
+        for round in rounds.into_iter() {
+            let ids: Vec<Bytes> = round
+                .iter()
+                .map(|id| Bytes::from_slice(id.as_bytes()))
+                .collect();
+            let batch = extend_new_ids(&mut need, &ids, |id| EventId::from_slice(id).ok());
+            if !batch.is_empty() {
+                batches.push(batch);
+            }
+        }
+
+        assert_eq!(batches, vec![vec![id1, id2], vec![id3]]);
+
+        let union: HashSet<EventId> = batches.into_iter().flatten().collect();
+        assert_eq!(union, need);
+    }
+}
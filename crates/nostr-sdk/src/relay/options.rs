@@ -8,15 +8,20 @@ use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use super::{AtomicRelayServiceFlags, RelayServiceFlags};
+use nostr::Event;
+
+use super::{AtomicRelayServiceFlags, Error, RelayServiceFlags, Transport};
 use crate::client::options::DEFAULT_SEND_TIMEOUT;
 
 pub const DEFAULT_RETRY_SEC: u64 = 10;
 pub const MIN_RETRY_SEC: u64 = 5;
 pub const MAX_ADJ_RETRY_SEC: u64 = 60;
+pub const DEFAULT_DEDUP_CACHE_SIZE: usize = 10_000;
 pub const NEGENTROPY_HIGH_WATER_UP: usize = 100;
 pub const NEGENTROPY_LOW_WATER_UP: usize = 50;
 pub const NEGENTROPY_BATCH_SIZE_DOWN: usize = 50;
+pub const NEGENTROPY_DEFAULT_ID_SIZE: u8 = 32;
+pub const NEGENTROPY_MIN_ID_SIZE: u8 = 8;
 
 /// [`Relay`](super::Relay) options
 #[derive(Debug, Clone)]
@@ -34,6 +39,19 @@ pub struct RelayOptions {
     retry_sec: Arc<AtomicU64>,
     /// Automatically adjust retry seconds based on success/attempts (default: true)
     adjust_retry_sec: Arc<AtomicBool>,
+    /// Max random delay added to each reconnection attempt (default: None)
+    pub(super) reconnect_jitter: Option<Duration>,
+    /// Max number of tags allowed in outgoing events (default: None)
+    pub(super) max_event_tags: Option<usize>,
+    /// Max content length allowed in outgoing events (default: None)
+    pub(super) max_content_length: Option<usize>,
+    /// Custom transport (default: None, use WebSocket)
+    pub(super) transport: Option<Arc<dyn Transport>>,
+    /// Re-send the REQs of the active subscriptions after a reconnection (default: true)
+    pub(super) resume_subscriptions: bool,
+    /// Fetch the NIP-11 document before subscribing (default: false)
+    #[cfg(feature = "nip11")]
+    pub(super) fetch_nip11_on_connect: bool,
 }
 
 impl Default for RelayOptions {
@@ -45,6 +63,13 @@ impl Default for RelayOptions {
             reconnect: Arc::new(AtomicBool::new(true)),
             retry_sec: Arc::new(AtomicU64::new(DEFAULT_RETRY_SEC)),
             adjust_retry_sec: Arc::new(AtomicBool::new(true)),
+            reconnect_jitter: None,
+            max_event_tags: None,
+            max_content_length: None,
+            transport: None,
+            resume_subscriptions: true,
+            #[cfg(feature = "nip11")]
+            fetch_nip11_on_connect: false,
         }
     }
 }
@@ -157,6 +182,88 @@ impl RelayOptions {
                 Some(adjust_retry_sec)
             });
     }
+
+    /// Add a random delay, in `[0, jitter]`, to each reconnection attempt (default: None)
+    ///
+    /// Avoid reconnecting all the relays at the same time (i.e. when the network comes back).
+    /// The delay is added on top of the retry interval, also when adjusted (see `adjust_retry_sec`).
+    pub fn reconnect_jitter(mut self, jitter: Option<Duration>) -> Self {
+        self.reconnect_jitter = jitter;
+        self
+    }
+
+    /// Max number of tags allowed in outgoing events (default: None)
+    ///
+    /// Events exceeding the limit are rejected locally, before sending them to the relay.
+    /// Usually set from the NIP-11 `max_event_tags` limitation.
+    pub fn max_event_tags(mut self, max_event_tags: Option<usize>) -> Self {
+        self.max_event_tags = max_event_tags;
+        self
+    }
+
+    /// Max content length (in characters) allowed in outgoing events (default: None)
+    ///
+    /// Events exceeding the limit are rejected locally, before sending them to the relay.
+    /// Usually set from the NIP-11 `max_content_length` limitation.
+    pub fn max_content_length(mut self, max_content_length: Option<usize>) -> Self {
+        self.max_content_length = max_content_length;
+        self
+    }
+
+    /// Set a custom [`Transport`] (default: WebSocket)
+    ///
+    /// Useful to run the relay over a custom transport or to inject a mock one in tests.
+    /// If set, the `proxy` option is ignored.
+    pub fn transport<T>(mut self, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Re-send the REQs of the active subscriptions after a reconnection (default: true)
+    ///
+    /// The subscriptions are resumed with the same [`SubscriptionId`](nostr::SubscriptionId).
+    /// One-shot queries (i.e. [`Relay::get_events_of`](super::Relay::get_events_of)) and the subscriptions
+    /// closed by the relay (`CLOSED`) are never resumed. If disabled, the subscriptions are forgotten on reconnection.
+    pub fn resume_subscriptions(mut self, enable: bool) -> Self {
+        self.resume_subscriptions = enable;
+        self
+    }
+
+    /// Fetch the NIP-11 relay information document on connect (default: false)
+    ///
+    /// If enabled, on the first connection attempt the document is fetched **before** opening the connection,
+    /// so the `limitation` section is available (see [`Relay::information`](super::Relay::information))
+    /// before any subscription is sent: the connection attempt (and so [`Relay::connect`](super::Relay::connect),
+    /// when called with a timeout) waits for the fetch, up to the connection timeout (60 secs if not set).
+    ///
+    /// On reconnections, or if disabled, the document is fetched in background.
+    #[cfg(feature = "nip11")]
+    pub fn fetch_nip11_on_connect(mut self, enable: bool) -> Self {
+        self.fetch_nip11_on_connect = enable;
+        self
+    }
+
+    /// Check if the [`Event`] is within the outgoing limits
+    pub(super) fn check_event_limits(&self, event: &Event) -> Result<(), Error> {
+        if let Some(max_tags) = self.max_event_tags {
+            let tags: usize = event.tags().len();
+            if tags > max_tags {
+                return Err(Error::EventExceedsMaxTags { tags, max_tags });
+            }
+        }
+
+        if let Some(max_length) = self.max_content_length {
+            let length: usize = event.content().chars().count();
+            if length > max_length {
+                return Err(Error::EventExceedsMaxContentLength { length, max_length });
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// [`Relay`](super::Relay) send options
@@ -166,6 +273,8 @@ pub struct RelaySendOptions {
     pub skip_disconnected: bool,
     /// Timeout for sending event (default: 10 secs)
     pub timeout: Duration,
+    /// Min number of relays that must accept the event (default: None)
+    pub min_confirmations: Option<usize>,
 }
 
 impl Default for RelaySendOptions {
@@ -173,6 +282,7 @@ impl Default for RelaySendOptions {
         Self {
             skip_disconnected: true,
             timeout: DEFAULT_SEND_TIMEOUT,
+            min_confirmations: None,
         }
     }
 }
@@ -200,6 +310,18 @@ impl RelaySendOptions {
             ..self
         }
     }
+
+    /// Min number of relays that must accept the event (default: None)
+    ///
+    /// When publishing to many relays, resolve as soon as `min` relays replied with `OK` `true`:
+    /// the remaining relays keep publishing in background.
+    /// If `None`, wait for all relays. `0` is treated as `1`.
+    pub fn min_confirmations(self, min: Option<usize>) -> Self {
+        Self {
+            min_confirmations: min,
+            ..self
+        }
+    }
 }
 
 /// Filter options
@@ -223,6 +345,10 @@ pub struct RelayPoolOptions {
     pub task_channel_size: usize,
     /// Shutdown on [RelayPool](super::pool::RelayPool) drop
     pub shutdown_on_drop: bool,
+    /// Deliver each event only once per subscription, also if received from more relays (default: false)
+    pub dedup_events: bool,
+    /// Max number of recent event ids tracked by `dedup_events` (default: 10000, `0` disables the deduplication)
+    pub dedup_cache_size: usize,
 }
 
 impl Default for RelayPoolOptions {
@@ -231,6 +357,8 @@ impl Default for RelayPoolOptions {
             notification_channel_size: 4096,
             task_channel_size: 4096,
             shutdown_on_drop: false,
+            dedup_events: false,
+            dedup_cache_size: DEFAULT_DEDUP_CACHE_SIZE,
         }
     }
 }
@@ -248,6 +376,27 @@ impl RelayPoolOptions {
             ..self
         }
     }
+
+    /// Suppress the notifications of the events already received from other relays (default: false)
+    ///
+    /// The ids of the recently received events are kept in a bounded LRU cache
+    /// (see [`RelayPoolOptions::dedup_cache_size`]): when full, the least recently seen ones are evicted.
+    pub fn dedup_events(self, value: bool) -> Self {
+        Self {
+            dedup_events: value,
+            ..self
+        }
+    }
+
+    /// Max number of event ids kept in the `dedup_events` cache (default: 10000)
+    ///
+    /// If `0`, the deduplication is disabled, also if [`RelayPoolOptions::dedup_events`] is enabled.
+    pub fn dedup_cache_size(self, size: usize) -> Self {
+        Self {
+            dedup_cache_size: size,
+            ..self
+        }
+    }
 }
 
 /// Negentropy Sync direction
@@ -271,11 +420,41 @@ impl NegentropyDirection {
     }
 }
 
+/// Negentropy protocol version
+///
+/// Only the peers using the same version can reconcile: a relay not supporting the requested one
+/// replies with the version it supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NegentropyProtocolVersion {
+    /// Version 1 (`0x61`), implemented by `strfry` and by the negentropy reference implementations since `v1.0`
+    ///
+    /// Not interoperable with the pre-release protocol (without the version byte).
+    V1,
+}
+
+impl Default for NegentropyProtocolVersion {
+    fn default() -> Self {
+        Self::V1
+    }
+}
+
+impl NegentropyProtocolVersion {
+    /// Get the version byte, sent as first byte of every negentropy message
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            Self::V1 => 0x61,
+        }
+    }
+}
+
 /// Negentropy reconciliation options
 #[derive(Debug, Clone, Copy)]
 pub struct NegentropyOptions {
     pub(super) initial_timeout: Duration,
     pub(super) direction: NegentropyDirection,
+    pub(super) dry_run: bool,
+    pub(super) protocol_version: NegentropyProtocolVersion,
+    pub(super) id_size: u8,
 }
 
 impl Default for NegentropyOptions {
@@ -283,6 +462,9 @@ impl Default for NegentropyOptions {
         Self {
             initial_timeout: Duration::from_secs(10),
             direction: NegentropyDirection::Down,
+            dry_run: false,
+            protocol_version: NegentropyProtocolVersion::default(),
+            id_size: NEGENTROPY_DEFAULT_ID_SIZE,
         }
     }
 }
@@ -306,4 +488,130 @@ impl NegentropyOptions {
         self.direction = direction;
         self
     }
+
+    /// Dry run (default: false)
+    ///
+    /// If `true`, only compute the reconciliation output (have/need ids) without sending or receiving events.
+    /// Both sets are reported, regardless of the [`NegentropyDirection`].
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Check if dry run is enabled
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Protocol version (default: [`NegentropyProtocolVersion::V1`])
+    ///
+    /// Checked against the relay reply to the `NEG-OPEN`: if the relay supports another version,
+    /// the reconciliation fails with [`Error::NegentropyProtocolVersionNotSupported`](super::Error::NegentropyProtocolVersionNotSupported).
+    pub fn protocol_version(mut self, protocol_version: NegentropyProtocolVersion) -> Self {
+        self.protocol_version = protocol_version;
+        self
+    }
+
+    /// Get protocol version
+    pub fn get_protocol_version(&self) -> NegentropyProtocolVersion {
+        self.protocol_version
+    }
+
+    /// ID size, in bytes (default: 32)
+    ///
+    /// The protocol allows truncated IDs, but both sides must use the same size:
+    /// use 32 bytes unless the relay requires otherwise.
+    /// With truncated IDs, the `have` and `need` IDs are resolved against the local items
+    /// passed to the reconciliation, so the local events can still be uploaded, while the missing ones
+    /// can't be downloaded (their full IDs are unknown) and are not included in the `need` set.
+    ///
+    /// Must be in the `8..=32` range: otherwise the reconciliation fails with
+    /// [`Error::InvalidNegentropyIdSize`](super::Error::InvalidNegentropyIdSize).
+    pub fn id_size(mut self, id_size: u8) -> Self {
+        self.id_size = id_size;
+        self
+    }
+
+    /// Get ID size, in bytes
+    pub fn get_id_size(&self) -> u8 {
+        self.id_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag};
+
+    use super::*;
+
+    #[test]
+    fn test_negentropy_options_dry_run() {
+        let opts = NegentropyOptions::new();
+        assert!(!opts.is_dry_run());
+
+        let opts = opts.dry_run(true);
+        assert!(opts.is_dry_run());
+        assert_eq!(opts.direction, NegentropyDirection::Down);
+    }
+
+    #[test]
+    fn test_negentropy_options_id_size() {
+        let opts = NegentropyOptions::new();
+        assert_eq!(opts.get_id_size(), 32);
+
+        let opts = opts.id_size(16);
+        assert_eq!(opts.get_id_size(), 16);
+
+        // Out of range values are rejected by the reconciliation
+        assert_eq!(NegentropyOptions::new().id_size(64).get_id_size(), 64);
+    }
+
+    #[test]
+    fn test_negentropy_options_protocol_version() {
+        let opts = NegentropyOptions::new();
+        assert_eq!(opts.get_protocol_version(), NegentropyProtocolVersion::V1);
+        assert_eq!(opts.get_protocol_version().as_u8(), 0x61);
+
+        let opts = opts.protocol_version(NegentropyProtocolVersion::V1);
+        assert_eq!(opts.get_protocol_version(), NegentropyProtocolVersion::V1);
+    }
+
+    #[test]
+    fn test_relay_options_event_limits() {
+        let keys = Keys::generate();
+        let tags = vec![Tag::Hashtag(String::from("nostr")); 3];
+        let event = EventBuilder::text_note("Hello", tags)
+            .to_event(&keys)
+            .unwrap();
+
+        // No limits
+        let opts = RelayOptions::new();
+        assert!(opts.check_event_limits(&event).is_ok());
+
+        // Within bounds
+        let opts = RelayOptions::new()
+            .max_event_tags(Some(3))
+            .max_content_length(Some(5));
+        assert!(opts.check_event_limits(&event).is_ok());
+
+        // Too many tags
+        let opts = RelayOptions::new().max_event_tags(Some(2));
+        assert!(matches!(
+            opts.check_event_limits(&event),
+            Err(Error::EventExceedsMaxTags {
+                tags: 3,
+                max_tags: 2
+            })
+        ));
+
+        // Content too long
+        let opts = RelayOptions::new().max_content_length(Some(4));
+        assert!(matches!(
+            opts.check_event_limits(&event),
+            Err(Error::EventExceedsMaxContentLength {
+                length: 5,
+                max_length: 4
+            })
+        ));
+    }
 }
@@ -4,11 +4,16 @@
 
 //! Relay Pool
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex, PoisonError};
+use std::task::{Context, Poll};
 use std::time::Duration;
 
+use async_utility::futures_util::stream::AbortHandle;
+use async_utility::futures_util::Stream;
 use async_utility::thread;
 use nostr::message::MessageHandleError;
 use nostr::nips::nip01::Coordinate;
@@ -23,8 +28,9 @@ use tokio::sync::{broadcast, Mutex, RwLock};
 
 use super::options::RelayPoolOptions;
 use super::{
-    Error as RelayError, FilterOptions, InternalSubscriptionId, Limits, NegentropyOptions, Relay,
-    RelayOptions, RelaySendOptions, RelayStatus,
+    Error as RelayError, FilterOptions, InternalSubscriptionId, Limits, NegentropyOptions,
+    ReconciliationOutput, Relay, RelayEvent, RelayOptions, RelaySendOptions, RelayStats,
+    RelayStatus,
 };
 use crate::util::TryIntoUrl;
 
@@ -64,6 +70,14 @@ pub enum Error {
     /// Event not published
     #[error("event not published")]
     EventNotPublished(EventId),
+    /// Not enough relays accepted the event
+    #[error("not enough confirmations: {confirmations}/{min}")]
+    NotEnoughConfirmations {
+        /// Relays that accepted the event
+        confirmations: usize,
+        /// Min required confirmations
+        min: usize,
+    },
     /// Events not published
     #[error("events not published")]
     EventsNotPublished,
@@ -128,12 +142,85 @@ pub enum RelayPoolNotification {
     Shutdown,
 }
 
+/// Event received for a subscription
+type SeenEvent = (SubscriptionId, EventId);
+
+/// Bounded LRU set of the recently received events, per subscription
+///
+/// The same event received by another subscription (i.e. a later query) is not a duplicate.
+/// When full, the least recently seen entry is evicted.
+#[derive(Debug)]
+struct SeenEventIds {
+    /// Events with the tick of their last use
+    ids: HashMap<SeenEvent, u64>,
+    /// Usage history, from the least recent: entries with an outdated tick are stale
+    order: VecDeque<(SeenEvent, u64)>,
+    tick: u64,
+    capacity: usize,
+}
+
+impl SeenEventIds {
+    fn new(capacity: usize) -> Self {
+        Self {
+            ids: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            tick: 0,
+            capacity,
+        }
+    }
+
+    /// Check if the event has already been seen, marking it as the most recently used
+    fn touch(&mut self, id: &SeenEvent) -> bool {
+        match self.ids.get_mut(id) {
+            Some(tick) => {
+                self.tick += 1;
+                *tick = self.tick;
+                self.order.push_back((id.clone(), self.tick));
+                self.compact();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, id: SeenEvent) {
+        if self.touch(&id) {
+            return;
+        }
+
+        // Evict the least recently used events, skipping the stale history entries
+        while self.ids.len() >= self.capacity {
+            match self.order.pop_front() {
+                Some((oldest, tick)) => {
+                    if self.ids.get(&oldest) == Some(&tick) {
+                        self.ids.remove(&oldest);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        self.tick += 1;
+        self.ids.insert(id.clone(), self.tick);
+        self.order.push_back((id, self.tick));
+    }
+
+    /// Drop the stale history entries, so the memory stays bounded
+    fn compact(&mut self) {
+        if self.order.len() > self.capacity.saturating_mul(2) {
+            let ids = &self.ids;
+            self.order.retain(|(id, tick)| ids.get(id) == Some(tick));
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct RelayPoolTask {
     database: Arc<DynNostrDatabase>,
     receiver: Arc<Mutex<Receiver<RelayPoolMessage>>>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
     running: Arc<AtomicBool>,
+    seen: Option<Arc<StdMutex<SeenEventIds>>>,
 }
 
 impl RelayPoolTask {
@@ -141,12 +228,14 @@ impl RelayPoolTask {
         database: Arc<DynNostrDatabase>,
         pool_task_receiver: Receiver<RelayPoolMessage>,
         notification_sender: broadcast::Sender<RelayPoolNotification>,
+        dedup_cache_size: Option<usize>,
     ) -> Self {
         Self {
             database,
             receiver: Arc::new(Mutex::new(pool_task_receiver)),
             notification_sender,
             running: Arc::new(AtomicBool::new(false)),
+            seen: dedup_cache_size.map(|size| Arc::new(StdMutex::new(SeenEventIds::new(size)))),
         }
     }
 
@@ -304,6 +393,18 @@ impl RelayPoolTask {
                     );
                 }
 
+                // Check if event was already received from another relay, for the same subscription
+                let subscription_id = SubscriptionId::new(subscription_id);
+                let seen_event: SeenEvent = (subscription_id.clone(), partial_event.id);
+                let already_received: bool = self.seen.as_ref().map_or(false, |seen| {
+                    let mut seen = seen.lock().unwrap_or_else(PoisonError::into_inner);
+                    seen.touch(&seen_event)
+                });
+                if already_received {
+                    tracing::trace!("Event {} already received, skipping", partial_event.id);
+                    return Ok(None);
+                }
+
                 // Check if event was already saved
                 if self
                     .database
@@ -325,6 +426,12 @@ impl RelayPoolTask {
                 // Verify event
                 event.verify()?;
 
+                // Mark as received only once verified, so an invalid copy can't suppress a valid one
+                if let Some(seen) = &self.seen {
+                    let mut seen = seen.lock().unwrap_or_else(PoisonError::into_inner);
+                    seen.insert(seen_event);
+                }
+
                 // Save event
                 self.database.save_event(&event).await?;
 
@@ -338,7 +445,7 @@ impl RelayPoolTask {
 
                 // Compose RelayMessage
                 Ok(Some(RelayMessage::Event {
-                    subscription_id: SubscriptionId::new(subscription_id),
+                    subscription_id,
                     event: Box::new(event),
                 }))
             }
@@ -347,6 +454,74 @@ impl RelayPoolTask {
     }
 }
 
+/// Stream of the events of a single subscription
+///
+/// Returned by [`RelayPool::stream_events_of`]: when dropped, the `CLOSE` is sent to the relays.
+pub struct EventStream {
+    id: SubscriptionId,
+    rx: Receiver<Event>,
+    ids: HashSet<EventId>,
+    relays: Vec<Relay>,
+    abort_handles: Vec<AbortHandle>,
+    terminated: bool,
+}
+
+impl fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventStream")
+            .field("id", &self.id)
+            .field("terminated", &self.terminated)
+            .finish()
+    }
+}
+
+impl EventStream {
+    /// Get [`SubscriptionId`]
+    pub fn id(&self) -> &SubscriptionId {
+        &self.id
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.rx.poll_recv(cx) {
+                Poll::Ready(Some(event)) => {
+                    // Skip events already received from other relays
+                    if self.ids.insert(event.id()) {
+                        return Poll::Ready(Some(event));
+                    }
+                }
+                Poll::Ready(None) => {
+                    self.terminated = true;
+                    return Poll::Ready(None);
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        for handle in self.abort_handles.iter() {
+            handle.abort();
+        }
+
+        // If terminated, the subscriptions are already closed
+        if !self.terminated {
+            for relay in self.relays.iter() {
+                let msg = ClientMessage::close(self.id.clone());
+                if let Err(e) = relay.send_relay_event(RelayEvent::SendMsg(Box::new(msg)), None) {
+                    tracing::error!("Impossible to close subscription with {}: {e}", relay.url());
+                }
+            }
+        }
+    }
+}
+
 /// Relay Pool
 #[derive(Debug, Clone)]
 pub struct RelayPool {
@@ -401,6 +576,7 @@ impl RelayPool {
             database.clone(),
             pool_task_receiver,
             notification_sender.clone(),
+            (opts.dedup_events && opts.dedup_cache_size > 0).then_some(opts.dedup_cache_size),
         );
 
         let pool = Self {
@@ -478,6 +654,17 @@ impl RelayPool {
         relays.get(&url).cloned().ok_or(Error::RelayNotFound)
     }
 
+    /// Get [`RelayStats`] of all relays
+    pub async fn stats(&self) -> HashMap<Url, RelayStats> {
+        let relays = self.relays().await;
+        let mut stats = HashMap::with_capacity(relays.len());
+        for (url, relay) in relays.into_iter() {
+            let status: RelayStatus = relay.status().await;
+            stats.insert(url, RelayStats::new(status, &relay.stats()));
+        }
+        stats
+    }
+
     /// Get subscription filters
     pub async fn subscription_filters(&self) -> Vec<Filter> {
         self.filters.read().await.clone()
@@ -646,42 +833,7 @@ impl RelayPool {
     /// Send event and wait for `OK` relay msg
     pub async fn send_event(&self, event: Event, opts: RelaySendOptions) -> Result<EventId, Error> {
         let relays = self.relays().await;
-
-        if relays.is_empty() {
-            return Err(Error::NoRelays);
-        }
-
-        self.database.save_event(&event).await?;
-
-        let sent_to_at_least_one_relay: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
-        let mut handles = Vec::new();
-
-        let event_id: EventId = event.id();
-
-        for (url, relay) in relays.into_iter() {
-            let event = event.clone();
-            let sent = sent_to_at_least_one_relay.clone();
-            let handle = thread::spawn(async move {
-                match relay.send_event(event, opts).await {
-                    Ok(_) => {
-                        let _ =
-                            sent.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| Some(true));
-                    }
-                    Err(e) => tracing::error!("Impossible to send event to {url}: {e}"),
-                }
-            });
-            handles.push(handle);
-        }
-
-        for handle in handles.into_iter().flatten() {
-            handle.join().await?;
-        }
-
-        if !sent_to_at_least_one_relay.load(Ordering::SeqCst) {
-            return Err(Error::EventNotPublished(event_id));
-        }
-
-        Ok(event_id)
+        self.publish_event(relays, event, opts).await
     }
 
     /// Send multiple [`Event`] at once
@@ -752,6 +904,100 @@ impl RelayPool {
         }
     }
 
+    /// Send event to a subset of the pool relays
+    ///
+    /// All the URLs must belong to relays already added to the pool, otherwise [`Error::RelayNotFound`] is returned
+    /// and the event is not sent. Relays are never added to the pool transiently.
+    ///
+    /// Succeed if the event is published to at least one of the selected relays.
+    pub async fn send_event_to_relays<I, U>(
+        &self,
+        urls: I,
+        event: Event,
+        opts: RelaySendOptions,
+    ) -> Result<EventId, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let mut relays = self.relays().await;
+
+        // Select relays
+        let mut selected: HashMap<Url, Relay> = HashMap::new();
+        for url in urls.into_iter() {
+            let url: Url = url.try_into_url()?;
+            match relays.remove(&url) {
+                Some(relay) => {
+                    selected.insert(url, relay);
+                }
+                None => {
+                    if !selected.contains_key(&url) {
+                        return Err(Error::RelayNotFound);
+                    }
+                }
+            }
+        }
+
+        self.publish_event(selected, event, opts).await
+    }
+
+    /// Save event into database, send it to `relays` and wait for `OK` relay msgs
+    ///
+    /// If [`RelaySendOptions::min_confirmations`] is set, return as soon as the quorum is reached.
+    async fn publish_event(
+        &self,
+        relays: HashMap<Url, Relay>,
+        event: Event,
+        opts: RelaySendOptions,
+    ) -> Result<EventId, Error> {
+        if relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+
+        self.database.save_event(&event).await?;
+
+        let event_id: EventId = event.id();
+        let (tx, mut rx) = mpsc::unbounded_channel::<bool>();
+
+        for (url, relay) in relays.into_iter() {
+            let event = event.clone();
+            let tx = tx.clone();
+            thread::spawn(async move {
+                let published: bool = match relay.send_event(event, opts).await {
+                    Ok(_) => true,
+                    Err(e) => {
+                        tracing::error!("Impossible to send event to {url}: {e}");
+                        false
+                    }
+                };
+                let _ = tx.send(published);
+            });
+        }
+
+        // Drop the original sender, so the receiver stops when all the relays have replied
+        drop(tx);
+
+        let min_confirmations: Option<usize> = opts.min_confirmations.map(|min| min.max(1));
+        let mut confirmations: usize = 0;
+        while let Some(published) = rx.recv().await {
+            if published {
+                confirmations += 1;
+
+                // Quorum reached: the remaining relays keep publishing in background
+                if min_confirmations.map_or(false, |min| confirmations >= min) {
+                    return Ok(event_id);
+                }
+            }
+        }
+
+        match min_confirmations {
+            Some(min) => Err(Error::NotEnoughConfirmations { confirmations, min }),
+            None if confirmations == 0 => Err(Error::EventNotPublished(event_id)),
+            None => Ok(event_id),
+        }
+    }
+
     /// Subscribe to filters
     ///
     /// Internal Subscription ID set to `InternalSubscriptionId::Pool`
@@ -837,6 +1083,72 @@ impl RelayPool {
         Ok(events.lock_owned().await.clone())
     }
 
+    /// Stream events of filters
+    ///
+    /// Send the `REQ` to all relays and return a dedicated [`EventStream`] (de-duplicated events).
+    /// Stored events in the database are **not** included.
+    /// The stream ends according to [`FilterOptions`] (or `timeout`, if no `EOSE` is received).
+    /// When dropped, the `CLOSE` is sent to the relays.
+    pub async fn stream_events_of(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> EventStream {
+        let id = SubscriptionId::generate();
+        let (tx, rx) = mpsc::channel::<Event>(1024);
+
+        let mut relays = Vec::new();
+        let mut abort_handles = Vec::new();
+        for (url, relay) in self.relays().await.into_iter() {
+            if !relay.flags().has_read() {
+                continue;
+            }
+
+            let id = id.clone();
+            let filters = filters.clone();
+            let tx = tx.clone();
+            let r = relay.clone();
+            let handle = thread::abortable(async move {
+                if let Err(e) = r
+                    .send_msg(ClientMessage::req(id.clone(), filters), None)
+                    .await
+                {
+                    tracing::error!("Impossible to send REQ to {url}: {e}");
+                    return;
+                }
+
+                if let Err(e) = r
+                    .handle_events_of(id.clone(), timeout, opts, |event| {
+                        let tx = tx.clone();
+                        async move {
+                            let _ = tx.send(event).await;
+                        }
+                    })
+                    .await
+                {
+                    tracing::error!("Failed to stream events from {url}: {e}");
+                }
+
+                // Unsubscribe
+                if let Err(e) = r.send_msg(ClientMessage::close(id), None).await {
+                    tracing::error!("Impossible to close subscription with {url}: {e}");
+                }
+            });
+            relays.push(relay);
+            abort_handles.push(handle);
+        }
+
+        EventStream {
+            id,
+            rx,
+            ids: HashSet::new(),
+            relays,
+            abort_handles,
+            terminated: false,
+        }
+    }
+
     /// Request events of filter.
     ///
     /// If the events aren't already stored in the database, will be sent to notification listener
@@ -905,36 +1217,405 @@ impl RelayPool {
     }
 
     /// Negentropy reconciliation
-    pub async fn reconcile(&self, filter: Filter, opts: NegentropyOptions) -> Result<(), Error> {
+    ///
+    /// Return the [`ReconciliationOutput`] of each relay (the failed ones are skipped).
+    pub async fn reconcile(
+        &self,
+        filter: Filter,
+        opts: NegentropyOptions,
+    ) -> Result<HashMap<Url, ReconciliationOutput>, Error> {
         let items: Vec<(EventId, Timestamp)> =
             self.database.negentropy_items(filter.clone()).await?;
         self.reconcile_with_items(filter, items, opts).await
     }
 
     /// Negentropy reconciliation with custom items
+    ///
+    /// Return the [`ReconciliationOutput`] of each relay (the failed ones are skipped).
     pub async fn reconcile_with_items(
         &self,
         filter: Filter,
         items: Vec<(EventId, Timestamp)>,
         opts: NegentropyOptions,
-    ) -> Result<(), Error> {
+    ) -> Result<HashMap<Url, ReconciliationOutput>, Error> {
         let mut handles = Vec::new();
         let relays = self.relays().await;
         for (url, relay) in relays.into_iter() {
             let filter = filter.clone();
             let my_items = items.clone();
             let handle = thread::spawn(async move {
-                if let Err(e) = relay.reconcile(filter, my_items, opts).await {
-                    tracing::error!("Failed to get reconcile with {url}: {e}");
+                match relay.reconcile(filter, my_items, opts).await {
+                    Ok(output) => Some((url, output)),
+                    Err(e) => {
+                        tracing::error!("Failed to get reconcile with {url}: {e}");
+                        None
+                    }
                 }
             });
             handles.push(handle);
         }
 
+        let mut outputs: HashMap<Url, ReconciliationOutput> = HashMap::new();
         for handle in handles.into_iter().flatten() {
-            handle.join().await?;
+            if let Some((url, output)) = handle.join().await? {
+                outputs.insert(url, output);
+            }
         }
 
-        Ok(())
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_utility::time;
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+    use crate::relay::mock::{self, MockTransport};
+    use crate::relay::NegentropyDirection;
+
+    #[test]
+    fn test_seen_event_ids_lru() {
+        let sub_id = SubscriptionId::new("lru");
+        let ids: Vec<SeenEvent> = (0..3u8)
+            .map(|i| (sub_id.clone(), EventId::from_slice(&[i; 32]).unwrap()))
+            .collect();
+
+        let mut seen = SeenEventIds::new(2);
+        seen.insert(ids[0].clone());
+        seen.insert(ids[1].clone());
+
+        // Hit: `ids[0]` becomes the most recently used, so `ids[1]` is evicted
+        assert!(seen.touch(&ids[0]));
+        seen.insert(ids[2].clone());
+        assert!(seen.touch(&ids[0]));
+        assert!(!seen.touch(&ids[1]));
+        assert!(seen.touch(&ids[2]));
+
+        // Same event, other subscription
+        assert!(!seen.touch(&(SubscriptionId::new("other"), ids[0].1)));
+
+        // The stale history entries are dropped
+        for _ in 0..10 {
+            seen.touch(&ids[0]);
+        }
+        assert!(seen.order.len() <= 4);
+    }
+
+    #[tokio::test]
+    async fn test_pool_stats() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello from mock transport", [])
+            .to_event(&keys)
+            .unwrap();
+
+        let online = Url::parse("ws://online.relay").unwrap();
+        let offline = Url::parse("ws://offline.relay").unwrap();
+
+        let pool = RelayPool::new(RelayPoolOptions::default());
+        let transport = MockTransport::new().replies(mock::events_replies(vec![event], true));
+        pool.add_relay(online.clone(), mock::mock_opts(transport))
+            .await
+            .unwrap();
+        let transport = MockTransport::new().unreachable();
+        pool.add_relay(offline.clone(), mock::mock_opts(transport))
+            .await
+            .unwrap();
+
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let stats = pool.stats().await;
+        assert_eq!(stats.get(&online).unwrap().events_received, 0);
+
+        pool.subscribe(vec![Filter::new().author(keys.public_key())], None)
+            .await;
+
+        let events_received = time::timeout(Some(Duration::from_secs(5)), async {
+            loop {
+                let stats = pool.stats().await;
+                let stats = stats.get(&online).unwrap();
+                if stats.events_received > 0 {
+                    return stats.events_received;
+                }
+                thread::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+        assert_eq!(events_received, Some(1));
+
+        let stats = pool.stats().await;
+        let online = stats.get(&online).unwrap();
+        assert_eq!(online.status, RelayStatus::Connected);
+        assert_eq!(online.attempts, 1);
+        assert!(online.last_activity.is_some());
+
+        let offline = stats.get(&offline).unwrap();
+        assert_eq!(offline.status, RelayStatus::Disconnected);
+        assert_eq!(offline.success, 0);
+        assert_eq!(offline.events_received, 0);
+        assert!(offline.last_activity.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pool_send_event_min_confirmations() {
+        let pool = RelayPool::new(RelayPoolOptions::default());
+        for i in 0..5 {
+            // 3 fast relays and 2 slow ones
+            let delay = if i < 3 {
+                Duration::from_millis(10)
+            } else {
+                Duration::from_secs(30)
+            };
+            let transport = MockTransport::new().replies(mock::ok_replies).delay(delay);
+            pool.add_relay(format!("ws://relay{i}.mock"), mock::mock_opts(transport))
+                .await
+                .unwrap();
+        }
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Quorum", [])
+            .to_event(&keys)
+            .unwrap();
+        let opts = RelaySendOptions::new()
+            .timeout(Some(Duration::from_secs(60)))
+            .min_confirmations(Some(3));
+        let res = time::timeout(
+            Some(Duration::from_secs(5)),
+            pool.send_event(event.clone(), opts),
+        )
+        .await;
+        assert_eq!(res.unwrap().unwrap(), event.id());
+    }
+
+    fn sent_event_ids(sent: &StdMutex<Vec<ClientMessage>>) -> Vec<EventId> {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .filter_map(|msg| match msg {
+                ClientMessage::Event(event) => Some(event.id()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pool_send_event_to_relays() {
+        let pool = RelayPool::new(RelayPoolOptions::default());
+        let mut sent = Vec::new();
+        for i in 0..3 {
+            let transport = MockTransport::new()
+                .replies(mock::ok_replies)
+                .delay(Duration::from_millis(10))
+                .record();
+            sent.push(transport.sent());
+            pool.add_relay(format!("ws://relay{i}.mock"), mock::mock_opts(transport))
+                .await
+                .unwrap();
+        }
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Subset", [])
+            .to_event(&keys)
+            .unwrap();
+        let res = time::timeout(
+            Some(Duration::from_secs(5)),
+            pool.send_event_to_relays(
+                ["ws://relay0.mock", "ws://relay2.mock"],
+                event.clone(),
+                RelaySendOptions::new(),
+            ),
+        )
+        .await;
+        assert_eq!(res.unwrap().unwrap(), event.id());
+
+        // Only the selected relays received the event
+        assert_eq!(sent_event_ids(&sent[0]), vec![event.id()]);
+        assert!(sent_event_ids(&sent[1]).is_empty());
+        assert_eq!(sent_event_ids(&sent[2]), vec![event.id()]);
+    }
+
+    async fn count_pool_event_messages(dedup: bool) -> usize {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello from two relays", [])
+            .to_event(&keys)
+            .unwrap();
+
+        let pool = RelayPool::new(RelayPoolOptions::new().dedup_events(dedup));
+        for url in ["ws://first.relay", "ws://second.relay"] {
+            let transport =
+                MockTransport::new().replies(mock::events_replies(vec![event.clone()], true));
+            pool.add_relay(url, mock::mock_opts(transport))
+                .await
+                .unwrap();
+        }
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let mut notifications = pool.notifications();
+        pool.subscribe(vec![Filter::new().author(keys.public_key())], None)
+            .await;
+
+        // Each relay sends the event followed by EOSE
+        let mut events: usize = 0;
+        let mut eose: usize = 0;
+        time::timeout(Some(Duration::from_secs(5)), async {
+            while eose < 2 {
+                if let Ok(RelayPoolNotification::Message { message, .. }) =
+                    notifications.recv().await
+                {
+                    match message {
+                        RelayMessage::Event { .. } => events += 1,
+                        RelayMessage::EndOfStoredEvents(..) => eose += 1,
+                        _ => (),
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        events
+    }
+
+    #[tokio::test]
+    async fn test_pool_dedup_events() {
+        assert_eq!(count_pool_event_messages(true).await, 1);
+        assert_eq!(count_pool_event_messages(false).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_pool_dedup_events_consecutive_queries() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hello again", [])
+            .to_event(&keys)
+            .unwrap();
+
+        let pool = RelayPool::new(RelayPoolOptions::new().dedup_events(true));
+        let transport =
+            MockTransport::new().replies(mock::events_replies(vec![event.clone()], true));
+        pool.add_relay("ws://mock.relay", mock::mock_opts(transport))
+            .await
+            .unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        // The event is a duplicate only within the same subscription
+        let filters = vec![Filter::new().author(keys.public_key())];
+        for _ in 0..2 {
+            let events = pool
+                .get_events_of(
+                    filters.clone(),
+                    Duration::from_secs(5),
+                    FilterOptions::ExitOnEOSE,
+                )
+                .await
+                .unwrap();
+            assert_eq!(events, vec![event.clone()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pool_stream_events_of() {
+        use async_utility::futures_util::StreamExt;
+
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..5)
+            .map(|i| {
+                EventBuilder::text_note(format!("Event #{i}"), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        // Events without EOSE
+        let transport = MockTransport::new()
+            .replies(mock::events_replies(events.clone(), false))
+            .record();
+        let sent = transport.sent();
+        let (pool, _relay) = mock::mock_pool_relay(transport).await;
+
+        let mut stream = pool
+            .stream_events_of(
+                vec![Filter::new().author(keys.public_key())],
+                Duration::from_secs(10),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await;
+        let id: SubscriptionId = stream.id().clone();
+
+        let mut received: Vec<Event> = Vec::new();
+        time::timeout(Some(Duration::from_secs(5)), async {
+            while let Some(event) = stream.next().await {
+                received.push(event);
+                if received.len() == 3 {
+                    break;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, events[..3].to_vec());
+
+        drop(stream);
+
+        let closed = time::timeout(Some(Duration::from_secs(5)), async {
+            loop {
+                let found = sent
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .any(|msg| msg == &ClientMessage::close(id.clone()));
+                if found {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        assert!(closed.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pool_reconcile_dry_run() {
+        let keys = Keys::generate();
+        let events: Vec<Event> = (0..3)
+            .map(|i| {
+                EventBuilder::text_note(format!("Event #{i}"), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        // Local: events 0 and 1, relay: events 1 and 2
+        let transport = MockTransport::new()
+            .replies(mock::negentropy_replies(
+                mock::negentropy_items(&events[1..]),
+                None,
+            ))
+            .record();
+        let sent = transport.sent();
+        let (pool, relay) = mock::mock_pool_relay(transport).await;
+        let opts = NegentropyOptions::new()
+            .direction(NegentropyDirection::Both)
+            .dry_run(true);
+        let outputs = time::timeout(
+            Some(Duration::from_secs(5)),
+            pool.reconcile_with_items(Filter::new(), mock::negentropy_items(&events[..2]), opts),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let output = outputs.get(&relay.url()).unwrap();
+        assert_eq!(output.have, HashSet::from([events[0].id()]));
+        assert_eq!(output.need, HashSet::from([events[2].id()]));
+
+        // Nothing uploaded nor downloaded
+        let sent = sent.lock().unwrap();
+        assert!(sent
+            .iter()
+            .any(|msg| matches!(msg, ClientMessage::NegOpen { .. })));
+        assert!(!sent.iter().any(|msg| msg.is_event() || msg.is_req()));
     }
 }
@@ -17,6 +17,7 @@ use nostr::Timestamp;
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::sync::RwLock;
 
+use super::RelayStatus;
 #[cfg(feature = "blocking")]
 use crate::RUNTIME;
 
@@ -92,6 +93,8 @@ pub struct RelayConnectionStats {
     success: Arc<AtomicUsize>,
     bytes_sent: Arc<AtomicUsize>,
     bytes_received: Arc<AtomicUsize>,
+    events_received: Arc<AtomicUsize>,
+    last_activity: Arc<AtomicU64>,
     connected_at: Arc<AtomicU64>,
     first_connection_timestamp: Arc<AtomicU64>,
     #[cfg(not(target_arch = "wasm32"))]
@@ -114,6 +117,8 @@ impl RelayConnectionStats {
             success: Arc::new(AtomicUsize::new(0)),
             bytes_sent: Arc::new(AtomicUsize::new(0)),
             bytes_received: Arc::new(AtomicUsize::new(0)),
+            events_received: Arc::new(AtomicUsize::new(0)),
+            last_activity: Arc::new(AtomicU64::new(0)),
             connected_at: Arc::new(AtomicU64::new(0)),
             first_connection_timestamp: Arc::new(AtomicU64::new(0)),
             #[cfg(not(target_arch = "wasm32"))]
@@ -154,6 +159,19 @@ impl RelayConnectionStats {
         self.bytes_received.load(Ordering::SeqCst)
     }
 
+    /// Events received
+    pub fn events_received(&self) -> usize {
+        self.events_received.load(Ordering::SeqCst)
+    }
+
+    /// Get UNIX timestamp of the last received message
+    pub fn last_activity(&self) -> Option<Timestamp> {
+        match self.last_activity.load(Ordering::SeqCst) {
+            0 => None,
+            timestamp => Some(Timestamp::from(timestamp)),
+        }
+    }
+
     /// Get UNIX timestamp of the last connection
     pub fn connected_at(&self) -> Timestamp {
         Timestamp::from(self.connected_at.load(Ordering::SeqCst))
@@ -208,6 +226,13 @@ impl RelayConnectionStats {
         if size > 0 {
             self.bytes_received.fetch_add(size, Ordering::SeqCst);
         }
+
+        let now: u64 = Timestamp::now().as_u64();
+        self.last_activity.store(now, Ordering::SeqCst);
+    }
+
+    pub(crate) fn new_event_received(&self) {
+        self.events_received.fetch_add(1, Ordering::SeqCst);
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -219,3 +244,36 @@ impl RelayConnectionStats {
         latencies.push_front(latency)
     }
 }
+
+/// Relay stats snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayStats {
+    /// Connection status
+    pub status: RelayStatus,
+    /// The number of times a connection has been attempted
+    pub attempts: usize,
+    /// The number of times a connection has been successfully established
+    pub success: usize,
+    /// Bytes sent
+    pub bytes_sent: usize,
+    /// Bytes received
+    pub bytes_received: usize,
+    /// Events received
+    pub events_received: usize,
+    /// UNIX timestamp of the last received message
+    pub last_activity: Option<Timestamp>,
+}
+
+impl RelayStats {
+    pub(crate) fn new(status: RelayStatus, stats: &RelayConnectionStats) -> Self {
+        Self {
+            status,
+            attempts: stats.attempts(),
+            success: stats.success(),
+            bytes_sent: stats.bytes_sent(),
+            bytes_received: stats.bytes_received(),
+            events_received: stats.events_received(),
+            last_activity: stats.last_activity(),
+        }
+    }
+}
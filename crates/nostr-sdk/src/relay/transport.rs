@@ -0,0 +1,227 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Transport
+
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use async_wsocket::futures_util::{Sink, SinkExt, Stream, StreamExt};
+use async_wsocket::WsMessage;
+use nostr::Url;
+use nostr_database::{AsyncTraitDeps, SendOutsideWasm};
+use thiserror::Error;
+
+/// [`Transport`] error
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Backend error
+    #[error("{0}")]
+    Backend(String),
+    /// Message not supported by the transport
+    #[error("message not supported")]
+    MessageNotSupported,
+}
+
+impl Error {
+    /// New backend error
+    pub fn backend<E>(error: E) -> Self
+    where
+        E: fmt::Display,
+    {
+        Self::Backend(error.to_string())
+    }
+}
+
+/// Transport message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportMessage {
+    /// Text message
+    Text(String),
+    /// Binary message
+    Binary(Vec<u8>),
+    /// Ping message
+    Ping(Vec<u8>),
+    /// Pong message
+    Pong(Vec<u8>),
+}
+
+impl TransportMessage {
+    /// Consume the message and return the payload
+    pub fn into_data(self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.into_bytes(),
+            Self::Binary(data) | Self::Ping(data) | Self::Pong(data) => data,
+        }
+    }
+}
+
+/// Transport used by [`Relay`](super::Relay) to communicate with the relay
+///
+/// The default one is [`WebSocketTransport`].
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Transport: AsyncTraitDeps {
+    /// Connect to the relay
+    async fn connect(
+        &self,
+        url: &Url,
+        timeout: Option<Duration>,
+    ) -> Result<(Box<dyn TransportSender>, Box<dyn TransportReceiver>), Error>;
+}
+
+/// Sending half of a [`Transport`] connection
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait TransportSender: SendOutsideWasm {
+    /// Send message
+    async fn send(&mut self, msg: TransportMessage) -> Result<(), Error>;
+
+    /// Send multiple messages at once
+    async fn send_batch(&mut self, msgs: Vec<TransportMessage>) -> Result<(), Error> {
+        for msg in msgs.into_iter() {
+            self.send(msg).await?;
+        }
+        Ok(())
+    }
+
+    /// Close connection
+    async fn close(&mut self) -> Result<(), Error>;
+}
+
+/// Receiving half of a [`Transport`] connection
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait TransportReceiver: SendOutsideWasm {
+    /// Receive next message
+    ///
+    /// Return `None` if the connection is closed.
+    async fn recv(&mut self) -> Option<Result<TransportMessage, Error>>;
+}
+
+/// WebSocket [`Transport`]
+#[derive(Debug, Clone, Default)]
+pub struct WebSocketTransport {
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy: Option<SocketAddr>,
+}
+
+impl WebSocketTransport {
+    /// New WebSocket [`Transport`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set proxy
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: Option<SocketAddr>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Transport for WebSocketTransport {
+    async fn connect(
+        &self,
+        url: &Url,
+        timeout: Option<Duration>,
+    ) -> Result<(Box<dyn TransportSender>, Box<dyn TransportReceiver>), Error> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let (tx, rx) = async_wsocket::native::connect(url, self.proxy, timeout)
+            .await
+            .map_err(Error::backend)?;
+        #[cfg(target_arch = "wasm32")]
+        let (tx, rx) = async_wsocket::wasm::connect(url, timeout)
+            .await
+            .map_err(Error::backend)?;
+        Ok((
+            Box::new(WebSocketSender { inner: tx }),
+            Box::new(WebSocketReceiver { inner: rx }),
+        ))
+    }
+}
+
+fn into_ws_message(msg: TransportMessage) -> Result<WsMessage, Error> {
+    match msg {
+        TransportMessage::Text(text) => Ok(WsMessage::Text(text)),
+        TransportMessage::Binary(data) => Ok(WsMessage::Binary(data)),
+        #[cfg(not(target_arch = "wasm32"))]
+        TransportMessage::Ping(data) => Ok(WsMessage::Ping(data)),
+        #[cfg(not(target_arch = "wasm32"))]
+        TransportMessage::Pong(data) => Ok(WsMessage::Pong(data)),
+        #[cfg(target_arch = "wasm32")]
+        TransportMessage::Ping(..) | TransportMessage::Pong(..) => Err(Error::MessageNotSupported),
+    }
+}
+
+struct WebSocketSender<S> {
+    inner: S,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<S> TransportSender for WebSocketSender<S>
+where
+    S: Sink<WsMessage> + Unpin + SendOutsideWasm,
+    S::Error: fmt::Display,
+{
+    async fn send(&mut self, msg: TransportMessage) -> Result<(), Error> {
+        let msg: WsMessage = into_ws_message(msg)?;
+        self.inner.send(msg).await.map_err(Error::backend)
+    }
+
+    async fn send_batch(&mut self, msgs: Vec<TransportMessage>) -> Result<(), Error> {
+        for msg in msgs.into_iter() {
+            let msg: WsMessage = into_ws_message(msg)?;
+            self.inner.feed(msg).await.map_err(Error::backend)?;
+        }
+        self.inner.flush().await.map_err(Error::backend)
+    }
+
+    async fn close(&mut self) -> Result<(), Error> {
+        self.inner.close().await.map_err(Error::backend)
+    }
+}
+
+struct WebSocketReceiver<S> {
+    inner: S,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl<S, E> TransportReceiver for WebSocketReceiver<S>
+where
+    S: Stream<Item = Result<WsMessage, E>> + Unpin + Send,
+    E: fmt::Display,
+{
+    async fn recv(&mut self) -> Option<Result<TransportMessage, Error>> {
+        let msg: WsMessage = match self.inner.next().await? {
+            Ok(msg) => msg,
+            Err(e) => return Some(Err(Error::backend(e))),
+        };
+        Some(Ok(match msg {
+            WsMessage::Text(text) => TransportMessage::Text(text),
+            WsMessage::Ping(data) => TransportMessage::Ping(data),
+            WsMessage::Pong(data) => TransportMessage::Pong(data),
+            msg => TransportMessage::Binary(msg.into_data()),
+        }))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[async_trait(?Send)]
+impl<S> TransportReceiver for WebSocketReceiver<S>
+where
+    S: Stream<Item = WsMessage> + Unpin,
+{
+    async fn recv(&mut self) -> Option<Result<TransportMessage, Error>> {
+        let msg: WsMessage = self.inner.next().await?;
+        Some(Ok(TransportMessage::Binary(msg.as_ref().to_vec())))
+    }
+}
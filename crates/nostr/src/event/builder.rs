@@ -1163,6 +1163,26 @@ mod tests {
         assert_eq!(event, deserialized);
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_auth_event_builder() {
+        let keys = Keys::generate();
+        let relay_url = Url::parse("wss://relay.damus.io").unwrap();
+        let event = EventBuilder::auth("challenge-string", relay_url.clone())
+            .to_event(&keys)
+            .unwrap();
+
+        assert_eq!(event.kind(), Kind::Authentication);
+        assert_eq!(
+            event.tags(),
+            &[
+                Tag::Challenge(String::from("challenge-string")),
+                Tag::Relay(UncheckedUrl::from(relay_url)),
+            ]
+        );
+        event.verify().unwrap();
+    }
+
     #[test]
     #[cfg(all(feature = "std", feature = "nip04"))]
     fn test_encrypted_direct_msg() {
@@ -11,7 +11,7 @@ use core::num::ParseIntError;
 use core::ops::{Add, Range};
 use core::str::FromStr;
 
-use serde::de::{Deserialize, Deserializer, Error, Visitor};
+use serde::de::{self, Deserialize, Deserializer, Visitor};
 use serde::ser::{Serialize, Serializer};
 
 /// NIP90 - Job request range
@@ -174,7 +174,47 @@ impl Hash for Kind {
     }
 }
 
+/// [`Kind`] error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Negative kind
+    Negative(i64),
+    /// Kind greater than `u16::MAX` (NIP-01 kinds are in the `0..=65535` range)
+    OutOfRange(u64),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Negative(kind) => write!(f, "negative kind: {kind}"),
+            Self::OutOfRange(kind) => {
+                write!(f, "kind out of range: {kind} (max {})", u16::MAX)
+            }
+        }
+    }
+}
+
 impl Kind {
+    /// Try to construct [`Kind`] from `i64`
+    ///
+    /// Return [`Error::Negative`] for negative values and
+    /// [`Error::OutOfRange`] for values greater than `u16::MAX`.
+    pub fn try_from_i64(kind: i64) -> Result<Self, Error> {
+        if kind < 0 {
+            return Err(Error::Negative(kind));
+        }
+
+        let kind: u64 = kind as u64;
+        if kind > u16::MAX as u64 {
+            return Err(Error::OutOfRange(kind));
+        }
+
+        Ok(Self::from(kind))
+    }
+
     /// Get [`Kind`] as `u32`
     pub fn as_u32(&self) -> u32 {
         self.as_u64() as u32
@@ -364,6 +404,22 @@ impl From<Kind> for u64 {
     }
 }
 
+impl From<u16> for Kind {
+    fn from(u: u16) -> Self {
+        Self::from(u as u64)
+    }
+}
+
+impl TryFrom<Kind> for u16 {
+    type Error = Error;
+
+    /// Return [`Error::OutOfRange`] for custom kinds greater than `u16::MAX`
+    fn try_from(kind: Kind) -> Result<Self, Self::Error> {
+        let kind: u64 = kind.as_u64();
+        u16::try_from(kind).map_err(|_| Error::OutOfRange(kind))
+    }
+}
+
 impl From<f64> for Kind {
     fn from(kind: f64) -> Self {
         Self::from(kind as u64)
@@ -412,14 +468,22 @@ impl Visitor<'_> for KindVisitor {
     type Value = Kind;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "an unsigned number")
+        write!(f, "an unsigned number in the 0..=65535 range")
     }
 
     fn visit_u64<E>(self, v: u64) -> Result<Kind, E>
     where
-        E: Error,
+        E: de::Error,
+    {
+        let kind: i64 = i64::try_from(v).map_err(|_| E::custom(Error::OutOfRange(v)))?;
+        self.visit_i64(kind)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Kind, E>
+    where
+        E: de::Error,
     {
-        Ok(From::<u64>::from(v))
+        Kind::try_from_i64(v).map_err(E::custom)
     }
 }
 
@@ -443,6 +507,55 @@ mod tests {
         assert_ne!(Kind::TextNote, Kind::Custom(2));
     }
 
+    #[test]
+    fn test_kind_try_from_i64() {
+        assert_eq!(Kind::try_from_i64(1), Ok(Kind::TextNote));
+        assert_eq!(Kind::try_from_i64(65535), Ok(Kind::Custom(65535)));
+        assert_eq!(Kind::try_from_i64(-1), Err(Error::Negative(-1)));
+        assert_eq!(Kind::try_from_i64(65536), Err(Error::OutOfRange(65536)));
+    }
+
+    #[test]
+    fn test_kind_deserialize_invalid() {
+        assert_eq!(serde_json::from_str::<Kind>("7").unwrap(), Kind::Reaction);
+
+        let err = serde_json::from_str::<Kind>("-1").unwrap_err();
+        assert!(err.to_string().contains("negative kind: -1"));
+
+        let err = serde_json::from_str::<Kind>("65536").unwrap_err();
+        assert!(err.to_string().contains("kind out of range: 65536"));
+
+        let err = serde_json::from_str::<Kind>("18446744073709551615").unwrap_err();
+        assert!(err.to_string().contains("kind out of range"));
+    }
+
+    #[test]
+    fn test_kind_u16_conversion() {
+        let kind = Kind::from(7u16);
+        assert_eq!(kind, Kind::Reaction);
+        assert_eq!(u16::try_from(kind), Ok(7));
+
+        assert_eq!(Kind::from(6u16), Kind::Repost);
+        assert_eq!(Kind::from(1984u16), Kind::Reporting);
+        assert_eq!(Kind::from(10000u16), Kind::MuteList);
+
+        // Custom kind round-trip
+        let kind = Kind::from(4321u16);
+        assert_eq!(kind, Kind::Custom(4321));
+        let num: u16 = kind.try_into().unwrap();
+        assert_eq!(num, 4321);
+
+        // Custom kinds out of the `u16` range
+        assert_eq!(
+            u16::try_from(Kind::Custom(70_000)),
+            Err(Error::OutOfRange(70_000))
+        );
+        assert_eq!(
+            u16::try_from(Kind::Custom(u64::MAX)),
+            Err(Error::OutOfRange(u64::MAX))
+        );
+    }
+
     #[test]
     fn test_kind_is_parameterized_replaceable() {
         assert!(Kind::ParameterizedReplaceable(32122).is_parameterized_replaceable());
@@ -159,6 +159,11 @@ impl Event {
         }
     }
 
+    /// Compose event from [`EventIntermediate`], keeping the JSON deserialization key order
+    pub(crate) fn from_intermediate(inner: EventIntermediate, deser_order: Vec<String>) -> Self {
+        Self { inner, deser_order }
+    }
+
     /// Deserialize [`Event`] from [`Value`]
     ///
     /// **This method NOT verify the signature!**
@@ -0,0 +1,498 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Bounded deserialization of the messages
+//!
+//! The [`MessageLimits`] are enforced while deserializing, element by element,
+//! so an oversized message is rejected before being fully allocated.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+
+use bitcoin::secp256k1::schnorr::Signature;
+use bitcoin::secp256k1::XOnlyPublicKey;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+
+use super::relay::NegentropyErrorCode;
+use super::subscription::FilterVisitor;
+use super::{ClientMessage, Filter, MessageHandleError, MessageLimits, RelayMessage};
+use crate::event::EventIntermediate;
+use crate::{Event, EventId, Kind, SubscriptionId, Tag, Timestamp};
+
+/// Deserialize [`ClientMessage`] from JSON, enforcing the `limits`
+pub(super) fn client_message(
+    json: &[u8],
+    limits: &MessageLimits,
+) -> Result<ClientMessage, MessageHandleError> {
+    let error: Cell<Option<MessageHandleError>> = Cell::new(None);
+    let seed = ClientMessageSeed(Bounded {
+        limits,
+        error: &error,
+    });
+    from_slice(json, seed, &error)
+}
+
+/// Deserialize [`RelayMessage`] from JSON, enforcing the `limits`
+pub(super) fn relay_message(
+    json: &[u8],
+    limits: &MessageLimits,
+) -> Result<RelayMessage, MessageHandleError> {
+    let error: Cell<Option<MessageHandleError>> = Cell::new(None);
+    let seed = RelayMessageSeed(Bounded {
+        limits,
+        error: &error,
+    });
+    from_slice(json, seed, &error)
+}
+
+fn from_slice<'de, S>(
+    json: &'de [u8],
+    seed: S,
+    error: &Cell<Option<MessageHandleError>>,
+) -> Result<S::Value, MessageHandleError>
+where
+    S: DeserializeSeed<'de>,
+{
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    seed.deserialize(&mut deserializer)
+        .and_then(|value| {
+            deserializer.end()?;
+            Ok(value)
+        })
+        // The message error is the cause of the JSON one, if any
+        .map_err(|e| error.take().unwrap_or(MessageHandleError::Json(e)))
+}
+
+/// Limits shared by the seeds, with the first [`MessageHandleError`] met
+#[derive(Clone, Copy)]
+struct Bounded<'a> {
+    limits: &'a MessageLimits,
+    error: &'a Cell<Option<MessageHandleError>>,
+}
+
+impl Bounded<'_> {
+    /// Keep the `error`, returning the deserializer one
+    fn fail<E>(&self, error: MessageHandleError) -> E
+    where
+        E: de::Error,
+    {
+        let e = E::custom(&error);
+        self.error.set(Some(error));
+        e
+    }
+
+    /// Deserialize the next element of the message
+    fn next<'de, A, T>(&self, seq: &mut A) -> Result<T, A::Error>
+    where
+        A: SeqAccess<'de>,
+        T: Deserialize<'de>,
+    {
+        seq.next_element()?
+            .ok_or_else(|| self.fail(MessageHandleError::InvalidMessageFormat))
+    }
+
+    /// Deserialize the next element of the message with `seed`
+    fn next_seed<'de, A, S>(&self, seq: &mut A, seed: S) -> Result<S::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+        S: DeserializeSeed<'de>,
+    {
+        seq.next_element_seed(seed)?
+            .ok_or_else(|| self.fail(MessageHandleError::InvalidMessageFormat))
+    }
+}
+
+/// Deserialize up to `max` elements with `seed`, then skip the others
+///
+/// Return the elements and the total number of them.
+fn next_elements<'de, A, S, F>(
+    seq: &mut A,
+    max: usize,
+    seed: F,
+) -> Result<(Vec<S::Value>, usize), A::Error>
+where
+    A: SeqAccess<'de>,
+    S: DeserializeSeed<'de>,
+    F: Fn() -> S,
+{
+    let mut elements: Vec<S::Value> = Vec::new();
+    while elements.len() < max {
+        match seq.next_element_seed(seed())? {
+            Some(element) => elements.push(element),
+            None => {
+                let len: usize = elements.len();
+                return Ok((elements, len));
+            }
+        }
+    }
+
+    let mut len: usize = elements.len();
+    while seq.next_element::<IgnoredAny>()?.is_some() {
+        len += 1;
+    }
+    Ok((elements, len))
+}
+
+/// Skip the remaining elements of the message
+fn skip_remaining<'de, A>(seq: &mut A) -> Result<(), A::Error>
+where
+    A: SeqAccess<'de>,
+{
+    while seq.next_element::<IgnoredAny>()?.is_some() {}
+    Ok(())
+}
+
+struct ClientMessageSeed<'a>(Bounded<'a>);
+
+impl<'de> DeserializeSeed<'de> for ClientMessageSeed<'_> {
+    type Value = ClientMessage;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ClientMessageSeed<'_> {
+    type Value = ClientMessage;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("client message array")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let bounded: Bounded = self.0;
+        let limits: &MessageLimits = bounded.limits;
+        let kind: String = bounded.next(&mut seq)?;
+        let msg: ClientMessage = match kind.as_str() {
+            // ["EVENT", <event JSON>]
+            "EVENT" => ClientMessage::event(bounded.next_seed(&mut seq, EventSeed(bounded))?),
+            // ["REQ", <subscription_id>, <filter JSON>, <filter JSON>...]
+            // ["COUNT", <subscription_id>, <filter JSON>, <filter JSON>...]
+            "REQ" | "COUNT" => {
+                let subscription_id: SubscriptionId = bounded.next(&mut seq)?;
+                let (filters, len) =
+                    next_elements(&mut seq, limits.max_filters, || FilterVisitor {
+                        max_values_per_tag: limits.max_values_per_tag,
+                    })?;
+                if len > limits.max_filters {
+                    return Err(bounded.fail(MessageHandleError::TooManyFilters {
+                        filters: len,
+                        max_filters: limits.max_filters,
+                    }));
+                }
+                if kind == "REQ" {
+                    ClientMessage::req(subscription_id, filters)
+                } else {
+                    ClientMessage::count(subscription_id, filters)
+                }
+            }
+            // ["CLOSE", <subscription_id>]
+            "CLOSE" => ClientMessage::close(bounded.next(&mut seq)?),
+            // ["AUTH", <event JSON>]
+            "AUTH" => ClientMessage::auth(bounded.next_seed(&mut seq, EventSeed(bounded))?),
+            // ["NEG-OPEN", <subscription ID string>, <filter>, <idSize>, <initialMessage, lowercase hex-encoded>]
+            "NEG-OPEN" => {
+                let subscription_id: SubscriptionId = bounded.next(&mut seq)?;
+                let filter: Filter = bounded.next_seed(
+                    &mut seq,
+                    FilterVisitor {
+                        max_values_per_tag: limits.max_values_per_tag,
+                    },
+                )?;
+                let id_size: u64 = bounded.next(&mut seq)?;
+                ClientMessage::NegOpen {
+                    subscription_id,
+                    filter: Box::new(filter),
+                    id_size: id_size as u8,
+                    initial_message: bounded.next(&mut seq)?,
+                }
+            }
+            // ["NEG-MSG", <subscription ID string>, <message, lowercase hex-encoded>]
+            "NEG-MSG" => ClientMessage::NegMsg {
+                subscription_id: bounded.next(&mut seq)?,
+                message: bounded.next(&mut seq)?,
+            },
+            // ["NEG-CLOSE", <subscription ID string>]
+            "NEG-CLOSE" => ClientMessage::NegClose {
+                subscription_id: bounded.next(&mut seq)?,
+            },
+            // ["QUERY", <event ID>]
+            "QUERY" => ClientMessage::Query {
+                specific_sid: bounded.next(&mut seq)?,
+            },
+            _ => return Err(bounded.fail(MessageHandleError::InvalidMessageFormat)),
+        };
+        skip_remaining(&mut seq)?;
+        Ok(msg)
+    }
+}
+
+struct RelayMessageSeed<'a>(Bounded<'a>);
+
+impl<'de> DeserializeSeed<'de> for RelayMessageSeed<'_> {
+    type Value = RelayMessage;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+/// `COUNT` payload
+#[derive(Deserialize)]
+struct CountPayload {
+    count: usize,
+}
+
+impl<'de> Visitor<'de> for RelayMessageSeed<'_> {
+    type Value = RelayMessage;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("relay message array")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let bounded: Bounded = self.0;
+        let kind: String = bounded.next(&mut seq)?;
+        let msg: RelayMessage = match kind.as_str() {
+            // ["NOTICE", <message>]
+            "NOTICE" => RelayMessage::Notice {
+                message: bounded.next(&mut seq)?,
+            },
+            // ["CLOSED", <subscription_id>, <message>]
+            "CLOSED" => RelayMessage::Closed {
+                subscription_id: bounded.next(&mut seq)?,
+                message: bounded.next(&mut seq)?,
+            },
+            // ["EVENT", <subscription id>, <event JSON>]
+            "EVENT" => RelayMessage::Event {
+                subscription_id: bounded.next(&mut seq)?,
+                event: Box::new(bounded.next_seed(&mut seq, EventSeed(bounded))?),
+            },
+            // ["EOSE", <subscription_id>]
+            "EOSE" => RelayMessage::EndOfStoredEvents(bounded.next(&mut seq)?),
+            // ["OK", <event_id>, <true|false>, <message>]
+            "OK" => {
+                let event_id: String = bounded.next(&mut seq)?;
+                RelayMessage::Ok {
+                    event_id: EventId::from_hex(event_id)
+                        .map_err(|e| bounded.fail::<A::Error>(MessageHandleError::EventId(e)))?,
+                    status: bounded.next(&mut seq)?,
+                    message: bounded.next(&mut seq)?,
+                }
+            }
+            // ["AUTH", <challenge>]
+            "AUTH" => RelayMessage::Auth {
+                challenge: bounded.next(&mut seq)?,
+            },
+            // ["COUNT", <subscription id>, {"count": <integer>}]
+            "COUNT" => {
+                let subscription_id: SubscriptionId = bounded.next(&mut seq)?;
+                let payload: CountPayload = bounded.next(&mut seq)?;
+                RelayMessage::Count {
+                    subscription_id,
+                    count: payload.count,
+                }
+            }
+            // ["NEG-MSG", <subscription ID string>, <message, lowercase hex-encoded>]
+            "NEG-MSG" => RelayMessage::NegMsg {
+                subscription_id: bounded.next(&mut seq)?,
+                message: bounded.next(&mut seq)?,
+            },
+            // ["NEG-ERR", <subscription ID string>, <reason-code>]
+            "NEG-ERR" => {
+                let subscription_id: SubscriptionId = bounded.next(&mut seq)?;
+                let code: String = bounded.next(&mut seq)?;
+                RelayMessage::NegErr {
+                    subscription_id,
+                    code: NegentropyErrorCode::from(code),
+                }
+            }
+            _ => return Err(bounded.fail(MessageHandleError::InvalidMessageFormat)),
+        };
+        skip_remaining(&mut seq)?;
+        Ok(msg)
+    }
+}
+
+/// Deserialize an [`Event`], limiting the content length and the number of tags
+struct EventSeed<'a>(Bounded<'a>);
+
+impl<'de> DeserializeSeed<'de> for EventSeed<'_> {
+    type Value = Event;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EventSeed<'_> {
+    type Value = Event;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("event object")
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let bounded: Bounded = self.0;
+        let mut id: Option<EventId> = None;
+        let mut pubkey: Option<XOnlyPublicKey> = None;
+        let mut created_at: Option<Timestamp> = None;
+        let mut kind: Option<Kind> = None;
+        let mut tags: Option<Vec<Tag>> = None;
+        let mut content: Option<String> = None;
+        let mut sig: Option<Signature> = None;
+        let mut deser_order: Vec<String> = Vec::with_capacity(7);
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "id" => next_field(&mut map, &mut id, "id")?,
+                "pubkey" => next_field(&mut map, &mut pubkey, "pubkey")?,
+                "created_at" => next_field(&mut map, &mut created_at, "created_at")?,
+                "kind" => next_field(&mut map, &mut kind, "kind")?,
+                "tags" => {
+                    if tags.is_some() {
+                        return Err(de::Error::duplicate_field("tags"));
+                    }
+                    tags = Some(map.next_value_seed(TagsSeed(bounded))?);
+                }
+                "content" => {
+                    if content.is_some() {
+                        return Err(de::Error::duplicate_field("content"));
+                    }
+                    content = Some(map.next_value_seed(ContentSeed(bounded))?);
+                }
+                "sig" => next_field(&mut map, &mut sig, "sig")?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    continue;
+                }
+            }
+            deser_order.push(key);
+        }
+
+        let inner = EventIntermediate {
+            id: id.ok_or_else(|| de::Error::missing_field("id"))?,
+            pubkey: pubkey.ok_or_else(|| de::Error::missing_field("pubkey"))?,
+            created_at: created_at.ok_or_else(|| de::Error::missing_field("created_at"))?,
+            kind: kind.ok_or_else(|| de::Error::missing_field("kind"))?,
+            tags: tags.ok_or_else(|| de::Error::missing_field("tags"))?,
+            content: content.ok_or_else(|| de::Error::missing_field("content"))?,
+            sig: sig.ok_or_else(|| de::Error::missing_field("sig"))?,
+        };
+        Ok(Event::from_intermediate(inner, deser_order))
+    }
+}
+
+/// Deserialize the value of a map entry, failing if the field was already set
+fn next_field<'de, M, T>(
+    map: &mut M,
+    field: &mut Option<T>,
+    name: &'static str,
+) -> Result<(), M::Error>
+where
+    M: MapAccess<'de>,
+    T: Deserialize<'de>,
+{
+    if field.is_some() {
+        return Err(de::Error::duplicate_field(name));
+    }
+    *field = Some(map.next_value()?);
+    Ok(())
+}
+
+/// Deserialize the event tags, failing if more than [`MessageLimits::max_num_tags`]
+struct TagsSeed<'a>(Bounded<'a>);
+
+impl<'de> DeserializeSeed<'de> for TagsSeed<'_> {
+    type Value = Vec<Tag>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TagsSeed<'_> {
+    type Value = Vec<Tag>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("array of tags")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let max_tags: usize = self.0.limits.max_num_tags;
+        let (tags, len) = next_elements(&mut seq, max_tags, || PhantomData::<Tag>)?;
+        if len > max_tags {
+            return Err(self.0.fail(MessageHandleError::TooManyTags {
+                tags: len,
+                max_tags,
+            }));
+        }
+        Ok(tags)
+    }
+}
+
+/// Deserialize the event content, failing if longer than [`MessageLimits::max_content_length`]
+struct ContentSeed<'a>(Bounded<'a>);
+
+impl<'de> DeserializeSeed<'de> for ContentSeed<'_> {
+    type Value = String;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ContentSeed<'_> {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let max_len: usize = self.0.limits.max_content_length;
+        if v.len() > max_len {
+            return Err(self.0.fail(MessageHandleError::ContentTooLong {
+                len: v.len(),
+                max_len,
+            }));
+        }
+        Ok(v.to_string())
+    }
+}
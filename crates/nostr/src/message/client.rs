@@ -14,7 +14,7 @@ use negentropy::{Bytes, Negentropy};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{json, Value};
 
-use super::{Filter, MessageHandleError, SubscriptionId};
+use super::{bounded, Filter, MessageHandleError, MessageLimits, SubscriptionId};
 use crate::{Event, EventId, JsonUtil};
 
 /// Messages sent by clients, received by relays
@@ -361,6 +361,29 @@ impl ClientMessage {
     }
 }
 
+impl ClientMessage {
+    /// Deserialize [`ClientMessage`] from JSON string, enforcing the [`MessageLimits`]
+    ///
+    /// Use it for messages received from untrusted sources:
+    /// for trusted ones [`JsonUtil::from_json`] can be used.
+    ///
+    /// **This method NOT verify the event signature!**
+    pub fn from_json_bounded<T>(json: T, limits: MessageLimits) -> Result<Self, MessageHandleError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let msg: &[u8] = json.as_ref();
+
+        if msg.is_empty() {
+            return Err(MessageHandleError::EmptyMsg);
+        }
+
+        limits.check_size(msg.len())?;
+
+        bounded::client_message(msg, &limits)
+    }
+}
+
 impl JsonUtil for ClientMessage {
     type Err = MessageHandleError;
 
@@ -409,6 +432,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_client_message_from_json_bounded() {
+        let limits = MessageLimits {
+            max_filters: 2,
+            max_values_per_tag: 1,
+            ..Default::default()
+        };
+
+        let msg = r##"["REQ","test",{"kinds":[4]},{"#t":["nostr"]}]"##;
+        assert_eq!(
+            ClientMessage::from_json_bounded(msg, limits).unwrap(),
+            ClientMessage::from_json(msg).unwrap()
+        );
+
+        assert!(matches!(
+            ClientMessage::from_json_bounded("", limits),
+            Err(MessageHandleError::EmptyMsg)
+        ));
+
+        let msg = r#"["REQ","test",{"kinds":[1]},{"kinds":[4]},{"kinds":[7]}]"#;
+        assert!(matches!(
+            ClientMessage::from_json_bounded(msg, limits),
+            Err(MessageHandleError::TooManyFilters {
+                filters: 3,
+                max_filters: 2
+            })
+        ));
+
+        // The exceeding filters are only counted
+        let msg = r#"["REQ","test",{"kinds":[1]},{"kinds":[4]},{"kinds":"invalid"}]"#;
+        assert!(matches!(
+            ClientMessage::from_json_bounded(msg, limits),
+            Err(MessageHandleError::TooManyFilters {
+                filters: 3,
+                max_filters: 2
+            })
+        ));
+
+        let msg = r##"["REQ","test",{"#t":["nostr","bitcoin"]}]"##;
+        assert!(matches!(
+            ClientMessage::from_json_bounded(msg, limits),
+            Err(MessageHandleError::Json(..))
+        ));
+
+        let msg = r#"["EVENT",{"id":"70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5","pubkey":"379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe","created_at":1612809991,"kind":1,"tags":[],"content":"test","sig":"273a9cd5d11455590f4359500bccb7a89428262b96b3ea87a756b770964472f8c3e87f5d5e64d8d2e859a71462a3f477b554565c4f2f326cb01dd7620db71502"}]"#;
+        let limits = MessageLimits {
+            max_content_length: 2,
+            ..Default::default()
+        };
+        assert!(matches!(
+            ClientMessage::from_json_bounded(msg, limits),
+            Err(MessageHandleError::ContentTooLong { len: 4, max_len: 2 })
+        ));
+
+        let msg = msg.replacen("EVENT", "AUTH", 1);
+        assert!(matches!(
+            ClientMessage::from_json_bounded(msg, limits),
+            Err(MessageHandleError::ContentTooLong { len: 4, max_len: 2 })
+        ));
+
+        let limits = MessageLimits {
+            max_values_per_tag: 1,
+            ..Default::default()
+        };
+        let msg = r##"["NEG-OPEN","test",{"#t":["nostr"]},32,"6100000200"]"##;
+        assert!(ClientMessage::from_json_bounded(msg, limits).is_ok());
+
+        let msg = r##"["NEG-OPEN","test",{"#t":["nostr","bitcoin"]},32,"6100000200"]"##;
+        assert!(matches!(
+            ClientMessage::from_json_bounded(msg, limits),
+            Err(MessageHandleError::Json(..))
+        ));
+    }
+
     #[test]
     fn test_client_message_custom_kind() {
         let pk = XOnlyPublicKey::from_str(
@@ -427,6 +524,24 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_client_message_auth() {
+        let keys = crate::Keys::generate();
+        let relay_url = crate::Url::parse("wss://relay.damus.io").unwrap();
+        let event = crate::EventBuilder::auth("challenge-string", relay_url)
+            .to_event(&keys)
+            .unwrap();
+
+        let msg = ClientMessage::auth(event.clone());
+        let json = msg.as_json();
+        assert_eq!(json, format!(r#"["AUTH",{}]"#, event.as_json()));
+        assert_eq!(ClientMessage::from_json(json).unwrap(), msg);
+
+        // Missing event
+        assert!(ClientMessage::from_json(r#"["AUTH"]"#).is_err());
+    }
+
     #[test]
     fn test_negative_timestamp() {
         let req = json!([
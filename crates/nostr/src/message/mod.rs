@@ -6,13 +6,16 @@
 
 use core::fmt;
 
+mod bounded;
 pub mod client;
 pub mod relay;
 pub mod subscription;
 
 pub use self::client::ClientMessage;
-pub use self::relay::{RawRelayMessage, RelayMessage};
-pub use self::subscription::{Alphabet, Filter, GenericTagValue, SingleLetterTag, SubscriptionId};
+pub use self::relay::{NoticeSeverity, RawRelayMessage, RelayMessage};
+pub use self::subscription::{
+    Alphabet, Filter, FilterUpdate, GenericTagValue, SingleLetterTag, SubscriptionId,
+};
 use crate::event;
 
 /// Messages error
@@ -28,6 +31,34 @@ pub enum MessageHandleError {
     Event(event::Error),
     /// Empty message
     EmptyMsg,
+    /// Message exceeds [`MessageLimits::max_size`]
+    MessageTooLarge {
+        /// Message size, in bytes
+        size: usize,
+        /// Max size
+        max_size: usize,
+    },
+    /// Event content exceeds [`MessageLimits::max_content_length`]
+    ContentTooLong {
+        /// Content length, in bytes
+        len: usize,
+        /// Max length
+        max_len: usize,
+    },
+    /// Event tags exceed [`MessageLimits::max_num_tags`]
+    TooManyTags {
+        /// Number of tags
+        tags: usize,
+        /// Max number of tags
+        max_tags: usize,
+    },
+    /// Filters exceed [`MessageLimits::max_filters`]
+    TooManyFilters {
+        /// Number of filters
+        filters: usize,
+        /// Max number of filters
+        max_filters: usize,
+    },
 }
 
 #[cfg(feature = "std")]
@@ -41,6 +72,19 @@ impl fmt::Display for MessageHandleError {
             Self::EventId(e) => write!(f, "EventId: {e}"),
             Self::Event(e) => write!(f, "Event: {e}"),
             Self::EmptyMsg => write!(f, "Received empty message"),
+            Self::MessageTooLarge { size, max_size } => {
+                write!(f, "Message too large: {size} bytes (max {max_size})")
+            }
+            Self::ContentTooLong { len, max_len } => {
+                write!(f, "Event content too long: {len} bytes (max {max_len})")
+            }
+            Self::TooManyTags { tags, max_tags } => {
+                write!(f, "Too many event tags: {tags} (max {max_tags})")
+            }
+            Self::TooManyFilters {
+                filters,
+                max_filters,
+            } => write!(f, "Too many filters: {filters} (max {max_filters})"),
         }
     }
 }
@@ -62,3 +106,44 @@ impl From<event::Error> for MessageHandleError {
         Self::Event(e)
     }
 }
+
+/// Limits used to deserialize messages received from untrusted sources
+///
+/// See `from_json_bounded` of [`RelayMessage`] and [`ClientMessage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLimits {
+    /// Maximum size of the message, in bytes
+    pub max_size: usize,
+    /// Maximum length of the event content, in bytes
+    pub max_content_length: usize,
+    /// Maximum number of event tags
+    pub max_num_tags: usize,
+    /// Maximum number of filters per `REQ` (or `COUNT`)
+    pub max_filters: usize,
+    /// Maximum number of values of each generic tag of a filter
+    pub max_values_per_tag: usize,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self {
+            max_size: 5_250_000,
+            max_content_length: 70_000,
+            max_num_tags: 2_000,
+            max_filters: 100,
+            max_values_per_tag: subscription::DEFAULT_MAX_GENERIC_TAG_VALUES,
+        }
+    }
+}
+
+impl MessageLimits {
+    pub(crate) fn check_size(&self, size: usize) -> Result<(), MessageHandleError> {
+        if size > self.max_size {
+            return Err(MessageHandleError::MessageTooLarge {
+                size,
+                max_size: self.max_size,
+            });
+        }
+        Ok(())
+    }
+}
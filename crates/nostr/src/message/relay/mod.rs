@@ -15,7 +15,7 @@ use serde_json::{json, Value};
 mod raw;
 
 pub use self::raw::RawRelayMessage;
-use super::MessageHandleError;
+use super::{bounded, MessageHandleError, MessageLimits};
 use crate::{Event, EventId, JsonUtil, SubscriptionId};
 
 /// Negentropy error code
@@ -82,6 +82,43 @@ impl<'de> Deserialize<'de> for NegentropyErrorCode {
     }
 }
 
+/// Severity of a `NOTICE` message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NoticeSeverity {
+    /// Informational
+    Info,
+    /// Warning (i.e. rate limits, auth prompts)
+    Warning,
+    /// Error (i.e. invalid or blocked messages)
+    Error,
+}
+
+impl NoticeSeverity {
+    /// Heuristically classify a `NOTICE` message, based on common keywords
+    ///
+    /// Return [`NoticeSeverity::Info`] if no keyword match.
+    pub fn classify(message: &str) -> Self {
+        const ERROR_KEYWORDS: [&str; 4] = ["error", "invalid", "blocked", "failed"];
+        const WARNING_KEYWORDS: [&str; 6] = [
+            "rate-limit",
+            "rate limit",
+            "ratelimit",
+            "slow down",
+            "auth-required",
+            "warning",
+        ];
+
+        let message: String = message.to_lowercase();
+        if ERROR_KEYWORDS.iter().any(|k| message.contains(k)) {
+            Self::Error
+        } else if WARNING_KEYWORDS.iter().any(|k| message.contains(k)) {
+            Self::Warning
+        } else {
+            Self::Info
+        }
+    }
+}
+
 /// Messages sent by relays, received by clients
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum RelayMessage {
@@ -228,6 +265,16 @@ impl RelayMessage {
         }
     }
 
+    /// Get the [`NoticeSeverity`] of a `NOTICE` message
+    ///
+    /// Return `None` for the other messages.
+    pub fn notice_severity(&self) -> Option<NoticeSeverity> {
+        match self {
+            Self::Notice { message } => Some(NoticeSeverity::classify(message)),
+            _ => None,
+        }
+    }
+
     fn as_value(&self) -> Value {
         match self {
             Self::Event {
@@ -291,6 +338,29 @@ impl JsonUtil for RelayMessage {
     }
 }
 
+impl RelayMessage {
+    /// Deserialize [`RelayMessage`] from JSON string, enforcing the [`MessageLimits`]
+    ///
+    /// Use it for messages received from untrusted sources:
+    /// for trusted ones [`JsonUtil::from_json`] can be used.
+    ///
+    /// **This method NOT verify the event signature!**
+    pub fn from_json_bounded<T>(json: T, limits: MessageLimits) -> Result<Self, MessageHandleError>
+    where
+        T: AsRef<[u8]>,
+    {
+        let msg: &[u8] = json.as_ref();
+
+        if msg.is_empty() {
+            return Err(MessageHandleError::EmptyMsg);
+        }
+
+        limits.check_size(msg.len())?;
+
+        bounded::relay_message(msg, &limits)
+    }
+}
+
 impl TryFrom<RawRelayMessage> for RelayMessage {
     type Error = MessageHandleError;
 
@@ -370,6 +440,33 @@ mod tests {
             handled_valid_notice_msg
         );
     }
+
+    #[test]
+    fn test_notice_severity() {
+        assert_eq!(
+            RelayMessage::notice("Invalid event format!").notice_severity(),
+            Some(NoticeSeverity::Error)
+        );
+        assert_eq!(
+            RelayMessage::notice("ERROR: bad req").notice_severity(),
+            Some(NoticeSeverity::Error)
+        );
+        assert_eq!(
+            RelayMessage::notice("pubkey blocked").notice_severity(),
+            Some(NoticeSeverity::Error)
+        );
+        assert_eq!(
+            RelayMessage::notice("rate-limited: slow down there chief").notice_severity(),
+            Some(NoticeSeverity::Warning)
+        );
+        assert_eq!(
+            RelayMessage::notice("Welcome to the relay! Please generate a separate key")
+                .notice_severity(),
+            Some(NoticeSeverity::Info)
+        );
+        assert_eq!(RelayMessage::auth("challenge").notice_severity(), None);
+    }
+
     #[test]
     fn test_handle_invalid_notice() {
         // Missing content
@@ -436,6 +533,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_relay_message_from_json_bounded() {
+        let limits = MessageLimits {
+            max_content_length: 10,
+            max_num_tags: 1,
+            ..Default::default()
+        };
+
+        let msg = r#"["EVENT", "random_string", {"id":"70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5","pubkey":"379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe","created_at":1612809991,"kind":1,"tags":[],"content":"test","sig":"273a9cd5d11455590f4359500bccb7a89428262b96b3ea87a756b770964472f8c3e87f5d5e64d8d2e859a71462a3f477b554565c4f2f326cb01dd7620db71502"}]"#;
+        assert_eq!(
+            RelayMessage::from_json_bounded(msg, limits).unwrap(),
+            RelayMessage::from_json(msg).unwrap()
+        );
+
+        let oversized = msg.replace(r#""content":"test""#, r#""content":"test test test""#);
+        assert!(matches!(
+            RelayMessage::from_json_bounded(oversized, limits),
+            Err(MessageHandleError::ContentTooLong {
+                len: 14,
+                max_len: 10
+            })
+        ));
+
+        let many_tags = msg.replace(r#""tags":[]"#, r#""tags":[["t","a"],["t","b"]]"#);
+        assert!(matches!(
+            RelayMessage::from_json_bounded(many_tags, limits),
+            Err(MessageHandleError::TooManyTags {
+                tags: 2,
+                max_tags: 1
+            })
+        ));
+
+        let limits = MessageLimits {
+            max_size: 16,
+            ..Default::default()
+        };
+        assert!(matches!(
+            RelayMessage::from_json_bounded(msg, limits),
+            Err(MessageHandleError::MessageTooLarge { max_size: 16, .. })
+        ));
+        assert!(RelayMessage::from_json_bounded(r#"["EOSE","sub"]"#, limits).is_ok());
+    }
+
     #[test]
     fn test_handle_invalid_event() {
         // Missing Event field
@@ -468,6 +608,27 @@ mod tests {
         assert!(RelayMessage::from_json(r#"["EOSE", 404]"#).is_err(),);
     }
 
+    #[test]
+    fn test_handle_valid_auth() {
+        let valid_auth_msg = r#"["AUTH","challenge-string"]"#;
+        let handled_valid_auth_msg = RelayMessage::auth("challenge-string");
+
+        assert_eq!(
+            RelayMessage::from_json(valid_auth_msg).unwrap(),
+            handled_valid_auth_msg
+        );
+        assert_eq!(handled_valid_auth_msg.as_json(), valid_auth_msg);
+    }
+
+    #[test]
+    fn test_handle_invalid_auth() {
+        // Missing challenge
+        assert!(RelayMessage::from_json(r#"["AUTH"]"#).is_err());
+
+        // The challenge is not a string
+        assert!(RelayMessage::from_json(r#"["AUTH", 404]"#).is_err());
+    }
+
     #[test]
     fn test_handle_valid_ok() {
         let valid_ok_msg = r#"["OK", "b1a649ebe8b435ec71d3784793f3bbf4b93e64e17568a741aecd4c7ddeafce30", true, "pow: difficulty 25>=24"]"#;
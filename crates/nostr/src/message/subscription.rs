@@ -8,7 +8,9 @@
 #[cfg(not(feature = "std"))]
 use alloc::collections::{BTreeMap as AllocMap, BTreeSet as AllocSet};
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::fmt;
+use core::str::FromStr;
 #[cfg(feature = "std")]
 use std::collections::{HashMap as AllocMap, HashSet as AllocSet};
 
@@ -18,19 +20,26 @@ use bitcoin::hashes::Hash;
 use bitcoin::secp256k1::rand::rngs::OsRng;
 use bitcoin::secp256k1::rand::RngCore;
 use bitcoin::secp256k1::XOnlyPublicKey;
-use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
 use serde::ser::{SerializeMap, Serializer};
 use serde::{Deserialize, Serialize};
 
-use crate::{EventId, JsonUtil, Kind, Timestamp};
+use crate::nips::nip01::Coordinate;
+use crate::nips::nip19::{Error as Nip19Error, FromBech32, Nip19};
+use crate::{EventId, JsonUtil, Kind, Timestamp, UncheckedUrl};
 
 type GenericTags = AllocMap<SingleLetterTag, AllocSet<GenericTagValue>>;
 
+/// Max number of kinds added by [`Filter::kinds_range`]
+pub const MAX_KINDS_RANGE_LEN: usize = 1000;
+/// Default max number of values per generic tag for [`Filter::from_json_bounded`]
+pub const DEFAULT_MAX_GENERIC_TAG_VALUES: usize = 5000;
+
 /// Alphabet Error
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum SingleLetterTagError {
     /// Invalid char
-    InvalidChar,
+    InvalidChar(char),
 }
 
 #[cfg(feature = "std")]
@@ -39,7 +48,7 @@ impl std::error::Error for SingleLetterTagError {}
 impl fmt::Display for SingleLetterTagError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::InvalidChar => write!(f, "invalid alphabet char"),
+            Self::InvalidChar(c) => write!(f, "invalid alphabet char: '{c}'"),
         }
     }
 }
@@ -131,7 +140,7 @@ impl SingleLetterTag {
             'x' | 'X' => Alphabet::X,
             'y' | 'Y' => Alphabet::Y,
             'z' | 'Z' => Alphabet::Z,
-            _ => return Err(SingleLetterTagError::InvalidChar),
+            c => return Err(SingleLetterTagError::InvalidChar(c)),
         };
 
         Ok(Self {
@@ -212,6 +221,21 @@ impl SingleLetterTag {
     pub fn is_uppercase(&self) -> bool {
         self.uppercase
     }
+
+    /// Check if two single-letter tags have the same letter, ignoring the case
+    pub fn same_letter(&self, other: &Self) -> bool {
+        self.character == other.character
+    }
+
+    /// Convert to `lowercase` single-letter tag
+    pub fn to_lowercase(&self) -> Self {
+        Self::lowercase(self.character)
+    }
+
+    /// Convert to `uppercase` single-letter tag
+    pub fn to_uppercase(&self) -> Self {
+        Self::uppercase(self.character)
+    }
 }
 
 impl fmt::Display for SingleLetterTag {
@@ -269,6 +293,15 @@ impl SubscriptionId {
         let hash = Sha256Hash::hash(&os_random).to_string();
         Self::new(&hash[..32])
     }
+
+    /// Check if matches `other`, ignoring surrounding ASCII whitespaces on both sides
+    ///
+    /// Useful to tolerate relays that mangle the subscription ID.
+    /// The [`PartialEq`] implementation is still strict.
+    pub fn matches(&self, other: &str) -> bool {
+        self.0.trim_matches(|c: char| c.is_ascii_whitespace())
+            == other.trim_matches(|c: char| c.is_ascii_whitespace())
+    }
 }
 
 impl fmt::Display for SubscriptionId {
@@ -308,6 +341,46 @@ pub enum GenericTagValue {
     String(String),
 }
 
+impl GenericTagValue {
+    /// Parse a generic tag value, coercing it according to the tag letter
+    ///
+    /// Only `p`/`P` (public key) and `e` (event ID) attempt typed parsing:
+    /// every other letter, or a value that fails to parse, is kept as [`GenericTagValue::String`].
+    pub fn parse(tag: SingleLetterTag, value: &str) -> Self {
+        match (tag.character, tag.is_lowercase()) {
+            (Alphabet::P, _) => match XOnlyPublicKey::from_str(value) {
+                Ok(pubkey) => Self::Pubkey(pubkey),
+                Err(_) => Self::String(value.to_string()),
+            },
+            (Alphabet::E, true) => match EventId::from_hex(value) {
+                Ok(id) => Self::EventId(id),
+                Err(_) => Self::String(value.to_string()),
+            },
+            _ => Self::String(value.to_string()),
+        }
+    }
+
+    /// Get the display strings of the values, sorted lexicographically regardless of the variant
+    ///
+    /// Used for the canonical serialization of the generic tags: relays echo back the values as strings,
+    /// so the order must not depend on the variant (as the derived [`Ord`] does).
+    pub fn sorted_by_display<'a, I>(values: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = &'a GenericTagValue>,
+    {
+        let mut values: Vec<String> = values.into_iter().map(|v| v.to_string()).collect();
+        values.sort();
+        values
+    }
+
+    fn coerce(self, tag: SingleLetterTag) -> Self {
+        match self {
+            Self::String(value) => Self::parse(tag, &value),
+            value => value,
+        }
+    }
+}
+
 impl fmt::Display for GenericTagValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -348,46 +421,40 @@ impl IntoGenericTagValue for &str {
 }
 
 /// Subscription filters
-#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct Filter {
     /// List of [`EventId`]
     #[serde(skip_serializing_if = "AllocSet::is_empty")]
-    #[serde(default)]
     pub ids: AllocSet<EventId>,
     /// List of [`XOnlyPublicKey`]
     #[serde(skip_serializing_if = "AllocSet::is_empty")]
-    #[serde(default)]
     pub authors: AllocSet<XOnlyPublicKey>,
     /// List of a kind numbers
     #[serde(skip_serializing_if = "AllocSet::is_empty")]
-    #[serde(default)]
     pub kinds: AllocSet<Kind>,
     /// It's a string describing a query in a human-readable form, i.e. "best nostr apps"
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/50.md>
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
     pub search: Option<String>,
     /// An integer unix timestamp, events must be newer than this to pass
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
     pub since: Option<Timestamp>,
     /// An integer unix timestamp, events must be older than this to pass
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
     pub until: Option<Timestamp>,
     /// Maximum number of events to be returned in the initial query
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
     pub limit: Option<usize>,
     /// Generic tag queries (NIP12)
-    #[serde(
-        flatten,
-        serialize_with = "serialize_generic_tags",
-        deserialize_with = "deserialize_generic_tags"
-    )]
-    #[serde(default)]
+    #[serde(flatten, serialize_with = "serialize_generic_tags")]
     pub generic_tags: GenericTags,
+    /// Authors to exclude when matching events locally (i.e. client-side mute)
+    ///
+    /// Not part of NIP-01: **never** serialized, so relays only see the positive filter.
+    /// For the same reason, it's ignored by [`Filter::is_empty`].
+    #[serde(skip)]
+    pub exclude_authors: AllocSet<XOnlyPublicKey>,
 }
 
 impl Filter {
@@ -403,10 +470,16 @@ impl Filter {
     }
 
     /// Add event ids or prefixes
+    ///
+    /// Under `std` the capacity is reserved up front, using the iterator size hint.
+    /// In `no_std` the ids are stored in a `BTreeSet`, that doesn't support reserving.
     pub fn ids<I>(mut self, ids: I) -> Self
     where
         I: IntoIterator<Item = EventId>,
     {
+        let ids = ids.into_iter();
+        #[cfg(feature = "std")]
+        self.ids.reserve(ids.size_hint().0);
         self.ids.extend(ids);
         self
     }
@@ -423,46 +496,115 @@ impl Filter {
     }
 
     /// Add author
-    pub fn author(mut self, author: XOnlyPublicKey) -> Self {
-        self.authors.insert(author);
+    pub fn author<P>(mut self, author: P) -> Self
+    where
+        P: Into<XOnlyPublicKey>,
+    {
+        self.authors.insert(author.into());
         self
     }
 
     /// Add authors
-    pub fn authors<I>(mut self, authors: I) -> Self
+    ///
+    /// Under `std` the capacity is reserved up front (see [`Filter::ids`]).
+    pub fn authors<I, P>(mut self, authors: I) -> Self
     where
-        I: IntoIterator<Item = XOnlyPublicKey>,
+        I: IntoIterator<Item = P>,
+        P: Into<XOnlyPublicKey>,
     {
-        self.authors.extend(authors);
+        let authors = authors.into_iter();
+        #[cfg(feature = "std")]
+        self.authors.reserve(authors.size_hint().0);
+        self.authors.extend(authors.map(|p| p.into()));
         self
     }
 
+    /// Add author from NIP19 `npub` or `nprofile` bech32 string
+    ///
+    /// The relay hints of `nprofile` are ignored.
+    pub fn author_bech32(self, author: &str) -> Result<Self, Nip19Error> {
+        let author: XOnlyPublicKey = public_key_from_bech32(author)?;
+        Ok(self.author(author))
+    }
+
+    /// Add authors from NIP19 `npub` or `nprofile` bech32 strings
+    ///
+    /// The relay hints of `nprofile` are ignored.
+    /// Return an error if any of the strings is invalid.
+    pub fn authors_bech32<I, S>(self, authors: I) -> Result<Self, Nip19Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let authors: Vec<XOnlyPublicKey> = authors
+            .into_iter()
+            .map(|a| public_key_from_bech32(a.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(self.authors(authors))
+    }
+
     /// Remove authors
-    pub fn remove_authors<I>(mut self, authors: I) -> Self
+    pub fn remove_authors<I, P>(mut self, authors: I) -> Self
     where
-        I: IntoIterator<Item = XOnlyPublicKey>,
+        I: IntoIterator<Item = P>,
+        P: Into<XOnlyPublicKey>,
     {
         for author in authors.into_iter() {
-            self.authors.remove(&author);
+            self.authors.remove(&author.into());
         }
         self
     }
 
+    /// Exclude authors when matching events locally (i.e. client-side mute)
+    ///
+    /// The exclusion set is **not** serialized: the relay still receives the positive filter.
+    pub fn exclude_authors<I, P>(mut self, authors: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<XOnlyPublicKey>,
+    {
+        self.exclude_authors
+            .extend(authors.into_iter().map(|p| p.into()));
+        self
+    }
+
     /// Add kind
-    pub fn kind(mut self, kind: Kind) -> Self {
-        self.kinds.insert(kind);
+    pub fn kind<K>(mut self, kind: K) -> Self
+    where
+        K: Into<Kind>,
+    {
+        self.kinds.insert(kind.into());
         self
     }
 
     /// Add kinds
-    pub fn kinds<I>(mut self, kinds: I) -> Self
+    ///
+    /// Under `std` the capacity is reserved up front (see [`Filter::ids`]).
+    pub fn kinds<I, K>(mut self, kinds: I) -> Self
     where
-        I: IntoIterator<Item = Kind>,
+        I: IntoIterator<Item = K>,
+        K: Into<Kind>,
     {
-        self.kinds.extend(kinds);
+        let kinds = kinds.into_iter();
+        #[cfg(feature = "std")]
+        self.kinds.reserve(kinds.size_hint().0);
+        self.kinds.extend(kinds.map(|k| k.into()));
         self
     }
 
+    /// Add all the kinds in the range (inclusive)
+    ///
+    /// At most [`MAX_KINDS_RANGE_LEN`] kinds are added: bigger ranges are truncated and a warning is logged.
+    pub fn kinds_range(self, start: u16, end: u16) -> Self {
+        let len: usize = (end as usize + 1).saturating_sub(start as usize);
+        if len > MAX_KINDS_RANGE_LEN {
+            tracing::warn!(
+                "Kinds range {start}..={end} contains {len} kinds: only the first {MAX_KINDS_RANGE_LEN} will be added"
+            );
+        }
+        self.kinds((start..=end).take(MAX_KINDS_RANGE_LEN))
+    }
+
     /// Remove kinds
     pub fn remove_kinds<I>(mut self, kinds: I) -> Self
     where
@@ -487,6 +629,16 @@ impl Filter {
         self.custom_tag(SingleLetterTag::lowercase(Alphabet::E), events)
     }
 
+    /// Add events from `(EventId, relay hint)` pairs (i.e. the content of `e` tags)
+    ///
+    /// Filters can't carry relay hints, so the hints are **dropped**.
+    pub fn events_with_hints<I>(self, events: I) -> Self
+    where
+        I: IntoIterator<Item = (EventId, Option<UncheckedUrl>)>,
+    {
+        self.events(events.into_iter().map(|(id, _)| id))
+    }
+
     /// Remove events
     pub fn remove_events<I>(self, events: I) -> Self
     where
@@ -496,24 +648,58 @@ impl Filter {
     }
 
     /// Add pubkey
-    pub fn pubkey(self, pubkey: XOnlyPublicKey) -> Self {
+    pub fn pubkey<P>(self, pubkey: P) -> Self
+    where
+        P: Into<XOnlyPublicKey>,
+    {
+        let pubkey: XOnlyPublicKey = pubkey.into();
         self.custom_tag(SingleLetterTag::lowercase(Alphabet::P), [pubkey])
     }
 
     /// Add pubkeys
-    pub fn pubkeys<I>(self, pubkeys: I) -> Self
+    pub fn pubkeys<I, P>(self, pubkeys: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<XOnlyPublicKey>,
+    {
+        self.custom_tag(
+            SingleLetterTag::lowercase(Alphabet::P),
+            pubkeys.into_iter().map(|p| p.into()),
+        )
+    }
+
+    /// Add pubkeys from `(XOnlyPublicKey, relay hint)` pairs (i.e. the content of `p` tags)
+    ///
+    /// Filters can't carry relay hints, so the hints are **dropped**.
+    pub fn pubkeys_with_hints<I, P>(self, pubkeys: I) -> Self
+    where
+        I: IntoIterator<Item = (P, Option<UncheckedUrl>)>,
+        P: Into<XOnlyPublicKey>,
+    {
+        self.pubkeys(pubkeys.into_iter().map(|(p, _)| p))
+    }
+
+    /// Mentions of `pubkey`: events of `kinds` that `p` tag the public key
+    ///
+    /// This is the standard notifications filter, equivalent to `Filter::new().pubkey(pubkey).kinds(kinds)`.
+    pub fn mentions<I, K>(self, pubkey: XOnlyPublicKey, kinds: I) -> Self
     where
-        I: IntoIterator<Item = XOnlyPublicKey>,
+        I: IntoIterator<Item = K>,
+        K: Into<Kind>,
     {
-        self.custom_tag(SingleLetterTag::lowercase(Alphabet::P), pubkeys)
+        self.pubkey(pubkey).kinds(kinds)
     }
 
     /// Remove pubkeys
-    pub fn remove_pubkeys<I>(self, pubkeys: I) -> Self
+    pub fn remove_pubkeys<I, P>(self, pubkeys: I) -> Self
     where
-        I: IntoIterator<Item = XOnlyPublicKey>,
+        I: IntoIterator<Item = P>,
+        P: Into<XOnlyPublicKey>,
     {
-        self.remove_custom_tag(SingleLetterTag::lowercase(Alphabet::P), pubkeys)
+        self.remove_custom_tag(
+            SingleLetterTag::lowercase(Alphabet::P),
+            pubkeys.into_iter().map(|p| p.into()),
+        )
     }
 
     /// Add hashtag
@@ -635,6 +821,48 @@ impl Filter {
         }
     }
 
+    /// Set search field from multiple terms (NIP50)
+    ///
+    /// Terms are joined with a space and preserved verbatim.
+    /// If `terms` is empty, the search field is left unset.
+    pub fn search_terms<I, S>(self, terms: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let terms: Vec<String> = terms.into_iter().map(|t| t.into()).collect();
+        Self {
+            search: if terms.is_empty() {
+                None
+            } else {
+                Some(terms.join(" "))
+            },
+            ..self
+        }
+    }
+
+    /// Append a term to the search field (NIP50)
+    ///
+    /// If the search field is unset, the term becomes the whole query.
+    pub fn add_search_term<S>(self, term: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let term: String = term.into();
+        let search: String = match self.search {
+            Some(mut search) if !search.is_empty() => {
+                search.push(' ');
+                search.push_str(&term);
+                search
+            }
+            _ => term,
+        };
+        Self {
+            search: Some(search),
+            ..self
+        }
+    }
+
     /// Remove search
     pub fn remove_search(self) -> Self {
         Self {
@@ -667,6 +895,26 @@ impl Filter {
         }
     }
 
+    /// Get the previous page (older events): set `until` to the timestamp of the oldest received event
+    ///
+    /// `until` is inclusive, so the events with `created_at == oldest` are returned again:
+    /// de-duplicate them by [`EventId`] and set a `limit`, to avoid to stall on a timestamp with many events.
+    ///
+    /// An existing `since` is kept: if `oldest < since` the window is empty (the bounds are **not** swapped).
+    pub fn page_before(self, oldest: Timestamp) -> Self {
+        self.until(oldest)
+    }
+
+    /// Get the next page (newer events): set `since` to the timestamp of the newest received event
+    ///
+    /// `since` is inclusive, so the events with `created_at == newest` are returned again:
+    /// de-duplicate them by [`EventId`].
+    ///
+    /// An existing `until` is kept: if `newest > until` the window is empty (the bounds are **not** swapped).
+    pub fn page_after(self, newest: Timestamp) -> Self {
+        self.since(newest)
+    }
+
     /// Remove until
     pub fn remove_until(self) -> Self {
         Self {
@@ -691,6 +939,25 @@ impl Filter {
         }
     }
 
+    /// Clamp limit
+    ///
+    /// Lower the `limit` to `max` if it's higher or unset (i.e. NIP-11 `max_limit`)
+    pub fn clamp_limit(mut self, max: usize) -> Self {
+        self.limit = Some(self.limit.map_or(max, |limit| limit.min(max)));
+        self
+    }
+
+    /// Clamp a list of filters
+    ///
+    /// Truncate the list to `max_filters` and clamp the `limit` of each filter to `max_limit` (i.e. NIP-11 `max_filters` and `max_limit`)
+    pub fn clamp_filters(filters: Vec<Self>, max_limit: usize, max_filters: usize) -> Vec<Self> {
+        filters
+            .into_iter()
+            .take(max_filters)
+            .map(|f| f.clamp_limit(max_limit))
+            .collect()
+    }
+
     /// Add custom tag
     pub fn custom_tag<I, T>(mut self, tag: SingleLetterTag, values: I) -> Self
     where
@@ -699,7 +966,7 @@ impl Filter {
     {
         let values: AllocSet<GenericTagValue> = values
             .into_iter()
-            .map(|v| v.into_generic_tag_value())
+            .map(|v| v.into_generic_tag_value().coerce(tag))
             .collect();
         self.generic_tags
             .entry(tag)
@@ -718,7 +985,7 @@ impl Filter {
     {
         let values: AllocSet<GenericTagValue> = values
             .into_iter()
-            .map(|v| v.into_generic_tag_value())
+            .map(|v| v.into_generic_tag_value().coerce(tag))
             .collect();
         self.generic_tags.entry(tag).and_modify(|list| {
             list.retain(|value| !values.contains(value));
@@ -728,138 +995,830 @@ impl Filter {
 
     /// Check if [`Filter`] is empty
     pub fn is_empty(&self) -> bool {
-        self == &Filter::default()
+        // `exclude_authors` is local-only: check the filter as seen by the relays
+        self.ids.is_empty()
+            && self.authors.is_empty()
+            && self.kinds.is_empty()
+            && self.search.is_none()
+            && self.since.is_none()
+            && self.until.is_none()
+            && self.limit.is_none()
+            && self.generic_tags.is_empty()
+    }
+
+    /// Check if the [`Filter`] can match at least one event
+    ///
+    /// Return `false` if `since` is greater than `until` (empty time window)
+    /// or if `limit` is `0`. NIP-01 doesn't define `limit: 0`, but relays
+    /// commonly return no stored events for it, so it's considered unsatisfiable.
+    pub fn is_satisfiable(&self) -> bool {
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since > until {
+                return false;
+            }
+        }
+
+        self.limit != Some(0)
     }
-}
 
-impl JsonUtil for Filter {
-    type Err = serde_json::Error;
-}
+    /// Split the [`Filter`] in one filter per kind
+    ///
+    /// All the other constraints are preserved.
+    /// Return the filter unchanged (single-element vec) if it has less than 2 kinds.
+    pub fn split_by_kind(&self) -> Vec<Filter> {
+        if self.kinds.len() < 2 {
+            return vec![self.clone()];
+        }
 
-fn serialize_generic_tags<S>(generic_tags: &GenericTags, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    let mut map = serializer.serialize_map(Some(generic_tags.len()))?;
-    for (tag, values) in generic_tags.iter() {
-        map.serialize_entry(&tag.to_string(), values)?;
+        let mut kinds: Vec<Kind> = self.kinds.iter().copied().collect();
+        kinds.sort();
+        kinds
+            .into_iter()
+            .map(|kind| Filter {
+                kinds: AllocSet::from([kind]),
+                ..self.clone()
+            })
+            .collect()
     }
-    map.end()
-}
 
-fn deserialize_generic_tags<'de, D>(deserializer: D) -> Result<GenericTags, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    struct GenericTagsVisitor;
+    /// Human-readable description of the [`Filter`]
+    ///
+    /// i.e. `events of kind 1 by 2 authors, tagged #t=[bitcoin,nostr], since 2024-01-01T00:00:00Z`
+    pub fn describe(&self) -> String {
+        let mut output: String = String::from("events");
 
-    impl<'de> Visitor<'de> for GenericTagsVisitor {
-        type Value = GenericTags;
+        if !self.ids.is_empty() {
+            output.push_str(&format!(" with {}", plural(self.ids.len(), "id", "ids")));
+        }
 
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("map in which the keys are \"#X\" for some character X")
+        if !self.kinds.is_empty() {
+            let mut kinds: Vec<Kind> = self.kinds.iter().copied().collect();
+            kinds.sort();
+            let kinds: Vec<String> = kinds.into_iter().map(|k| k.to_string()).collect();
+            let label: &str = if kinds.len() == 1 { "kind" } else { "kinds" };
+            output.push_str(&format!(" of {label} {}", kinds.join(", ")));
         }
 
-        fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
-        where
-            M: MapAccess<'de>,
+        if !self.authors.is_empty() {
+            output.push_str(&format!(
+                " by {}",
+                plural(self.authors.len(), "author", "authors")
+            ));
+        }
+
+        let mut tags: Vec<(&SingleLetterTag, &AllocSet<GenericTagValue>)> =
+            self.generic_tags.iter().collect();
+        tags.sort_by_key(|(tag, _)| **tag);
+        for (tag, values) in tags.into_iter() {
+            let values: Vec<String> = GenericTagValue::sorted_by_display(values);
+            output.push_str(&format!(", tagged #{tag}=[{}]", values.join(",")));
+        }
+
+        if let Some(search) = &self.search {
+            output.push_str(&format!(", matching \"{search}\""));
+        }
+
+        if let Some(since) = self.since {
+            output.push_str(&format!(", since {}", since.to_human_datetime()));
+        }
+
+        if let Some(until) = self.until {
+            output.push_str(&format!(", until {}", until.to_human_datetime()));
+        }
+
+        if let Some(limit) = self.limit {
+            output.push_str(&format!(", limited to {limit}"));
+        }
+
+        if output == "events" {
+            return String::from("all events");
+        }
+
+        output
+    }
+
+    /// Get the [`Coordinate`]s targeted by the [`Filter`]
+    ///
+    /// Return a coordinate for each combination of `authors`, `kinds` and `#d` identifiers.
+    ///
+    /// Return an empty vec if the filter doesn't cleanly map to coordinates:
+    /// empty `authors`, `kinds` or `#d`, kinds that aren't parameterized replaceable
+    /// or other constraints (`ids`, `search` or other generic tags).
+    /// The `since`, `until` and `limit` fields are ignored.
+    pub fn to_coordinates(&self) -> Vec<Coordinate> {
+        let d: SingleLetterTag = SingleLetterTag::lowercase(Alphabet::D);
+
+        if self.authors.is_empty()
+            || self.kinds.is_empty()
+            || !self.ids.is_empty()
+            || self.search.is_some()
+            || self.generic_tags.keys().any(|tag| tag != &d)
+            || !self.kinds.iter().all(|k| k.is_parameterized_replaceable())
         {
-            let mut generic_tags = AllocMap::new();
-            while let Some(key) = map.next_key::<String>()? {
-                let mut chars = key.chars();
-                if let (Some('#'), Some(ch), None) = (chars.next(), chars.next(), chars.next()) {
-                    let tag: SingleLetterTag =
-                        SingleLetterTag::from_char(ch).map_err(serde::de::Error::custom)?;
-                    let mut values: AllocSet<GenericTagValue> = map.next_value()?;
-
-                    // Check if char is lowercase
-                    if tag.is_lowercase() {
-                        match tag.character {
-                            Alphabet::P => {
-                                values.retain(|v| matches!(v, GenericTagValue::Pubkey(_)))
-                            }
-                            Alphabet::E => {
-                                values.retain(|v| matches!(v, GenericTagValue::EventId(_)))
-                            }
-                            _ => {}
-                        }
-                    } else if tag.character == Alphabet::P {
-                        values.retain(|v| matches!(v, GenericTagValue::Pubkey(_)))
-                    }
+            return Vec::new();
+        }
+
+        let identifiers = match self.generic_tags.get(&d) {
+            Some(identifiers) if !identifiers.is_empty() => identifiers,
+            _ => return Vec::new(),
+        };
 
-                    generic_tags.insert(tag, values);
-                } else {
-                    map.next_value::<serde::de::IgnoredAny>()?;
+        let mut coordinates: Vec<Coordinate> =
+            Vec::with_capacity(self.authors.len() * self.kinds.len() * identifiers.len());
+        for author in self.authors.iter() {
+            for kind in self.kinds.iter() {
+                for identifier in identifiers.iter() {
+                    coordinates
+                        .push(Coordinate::new(*kind, *author).identifier(identifier.to_string()));
                 }
             }
-            Ok(generic_tags)
         }
+        coordinates
     }
 
-    deserializer.deserialize_map(GenericTagsVisitor)
-}
+    /// Estimate the size (in bytes) of the JSON serialized [`Filter`]
+    ///
+    /// The size is computed without allocating the JSON string, so it's cheap to sum it across many filters.
+    pub fn estimated_size(&self) -> usize {
+        let mut entries: usize = 0;
+        let mut size: usize = 0;
+
+        let mut add_entry = |key: &str, value_size: usize| {
+            entries += 1;
+            size += json_str_size(key) + 1 + value_size;
+        };
 
-#[cfg(test)]
-mod test {
-    use core::str::FromStr;
+        if !self.ids.is_empty() {
+            add_entry(
+                "ids",
+                json_array_size(self.ids.iter().map(|_| HEX_STR_SIZE)),
+            );
+        }
 
-    use super::*;
+        if !self.authors.is_empty() {
+            add_entry(
+                "authors",
+                json_array_size(self.authors.iter().map(|_| HEX_STR_SIZE)),
+            );
+        }
 
-    #[test]
-    fn test_kind_concatenation() {
-        let filter = Filter::new()
-            .kind(Kind::Metadata)
-            .kind(Kind::TextNote)
-            .kind(Kind::ContactList)
-            .kinds([
-                Kind::EncryptedDirectMessage,
-                Kind::Metadata,
-                Kind::LongFormTextNote,
-            ]);
-        assert_eq!(
-            filter,
-            Filter::new().kinds([
-                Kind::Metadata,
-                Kind::TextNote,
-                Kind::ContactList,
-                Kind::EncryptedDirectMessage,
-                Kind::LongFormTextNote
-            ])
-        );
-    }
+        if !self.kinds.is_empty() {
+            add_entry(
+                "kinds",
+                json_array_size(self.kinds.iter().map(|k| num_digits(k.as_u64()))),
+            );
+        }
 
-    #[test]
-    fn test_remove_ids() {
-        let event_id =
-            EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
-                .unwrap();
-        let filter = Filter::new().id(EventId::all_zeros()).id(event_id);
-        let filter = filter.remove_ids([EventId::all_zeros()]);
-        assert_eq!(filter, Filter::new().id(event_id));
-    }
+        if let Some(search) = &self.search {
+            add_entry("search", json_str_size(search));
+        }
 
-    #[test]
-    fn test_remove_custom_tag() {
-        let filter =
-            Filter::new().custom_tag(SingleLetterTag::lowercase(Alphabet::C), ["test", "test2"]);
-        let filter = filter.remove_custom_tag(SingleLetterTag::lowercase(Alphabet::C), ["test2"]);
-        assert_eq!(
-            filter,
-            Filter::new().custom_tag(SingleLetterTag::lowercase(Alphabet::C), ["test"])
-        );
-    }
+        if let Some(since) = &self.since {
+            add_entry("since", timestamp_digits(since));
+        }
 
-    #[test]
-    fn test_add_remove_event_tag() {
-        let mut filter = Filter::new().identifier("myidentifier");
-        filter = filter.custom_tag(SingleLetterTag::lowercase(Alphabet::D), ["mysecondid"]);
-        filter = filter.identifiers(["test", "test2"]);
-        filter = filter.remove_custom_tag(SingleLetterTag::lowercase(Alphabet::D), ["test2"]);
-        filter = filter.remove_identifiers(["mysecondid"]);
-        assert_eq!(filter, Filter::new().identifiers(["myidentifier", "test"]));
+        if let Some(until) = &self.until {
+            add_entry("until", timestamp_digits(until));
+        }
+
+        if let Some(limit) = self.limit {
+            add_entry("limit", num_digits(limit as u64));
+        }
+
+        for values in self.generic_tags.values() {
+            // Key: "#X"
+            add_entry(
+                "#X",
+                json_array_size(values.iter().map(|value| match value {
+                    GenericTagValue::Pubkey(..) | GenericTagValue::EventId(..) => HEX_STR_SIZE,
+                    GenericTagValue::String(s) => json_str_size(s),
+                })),
+            );
+        }
+
+        // Braces and commas
+        2 + size + entries.saturating_sub(1)
     }
 
-    #[test]
+    /// Get the [`Filter`]s needed to cover `self` but not `already_covered`
+    ///
+    /// Return an empty vec if `self` is already covered.
+    ///
+    /// Only the following cases are computed exactly:
+    /// * filters that differ only in the `since`/`until` window;
+    /// * filters that differ only in the `authors`.
+    ///
+    /// For any other combination, `self` is returned as is (so the overlap will be requested again).
+    ///
+    /// The same applies if either filter has a `limit`: a limited filter may not have returned
+    /// all the matching events, and splitting a limited `self` would change the number of events requested.
+    pub fn difference(&self, already_covered: &Filter) -> Vec<Filter> {
+        if self == already_covered {
+            return Vec::new();
+        }
+
+        // Not computable with a `limit`
+        if self.limit.is_some() || already_covered.limit.is_some() {
+            return vec![self.clone()];
+        }
+
+        // Differ only in `since`/`until`
+        if self.clone().remove_since().remove_until()
+            == already_covered.clone().remove_since().remove_until()
+        {
+            return self.window_difference(already_covered);
+        }
+
+        // Differ only in `authors`
+        let mut this: Filter = self.clone();
+        this.authors.clear();
+        let mut other: Filter = already_covered.clone();
+        other.authors.clear();
+        if this == other {
+            // Empty `authors` match any author
+            if already_covered.authors.is_empty() {
+                return Vec::new();
+            }
+
+            if !self.authors.is_empty() {
+                let authors: AllocSet<XOnlyPublicKey> = self
+                    .authors
+                    .difference(&already_covered.authors)
+                    .copied()
+                    .collect();
+                if authors.is_empty() {
+                    return Vec::new();
+                }
+                this.authors = authors;
+                return vec![this];
+            }
+        }
+
+        vec![self.clone()]
+    }
+
+    fn window_difference(&self, already_covered: &Filter) -> Vec<Filter> {
+        let mut filters: Vec<Filter> = Vec::new();
+
+        // Window before `already_covered.since`
+        if let Some(covered_since) = already_covered.since {
+            if self.since.map_or(true, |since| since < covered_since) {
+                let until: Timestamp = covered_since - 1_i64;
+                let until: Timestamp = match self.until {
+                    Some(self_until) if self_until < until => self_until,
+                    _ => until,
+                };
+                filters.push(self.clone().until(until));
+            }
+        }
+
+        // Window after `already_covered.until`
+        if let Some(covered_until) = already_covered.until {
+            if self.until.map_or(true, |until| until > covered_until) {
+                let since: Timestamp = covered_until + 1_i64;
+                let since: Timestamp = match self.since {
+                    Some(self_since) if self_since > since => self_since,
+                    _ => since,
+                };
+                filters.push(self.clone().since(since));
+            }
+        }
+
+        filters
+    }
+}
+
+impl JsonUtil for Filter {
+    type Err = serde_json::Error;
+}
+
+impl Filter {
+    /// Deserialize [`Filter`] from JSON, limiting the number of values of each generic tag
+    ///
+    /// Return an error if a generic tag has more than `max_values_per_tag` values
+    /// (see [`DEFAULT_MAX_GENERIC_TAG_VALUES`]). The values are counted while deserializing,
+    /// so an oversized tag is rejected before being fully allocated.
+    /// Use it for untrusted input: for trusted input [`JsonUtil::from_json`] can be used.
+    pub fn from_json_bounded<T>(
+        json: T,
+        max_values_per_tag: usize,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        let mut deserializer = serde_json::Deserializer::from_slice(json.as_ref());
+        let filter: Self =
+            (&mut deserializer).deserialize_map(FilterVisitor { max_values_per_tag })?;
+        deserializer.end()?;
+        Ok(filter)
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(FilterVisitor {
+            max_values_per_tag: usize::MAX,
+        })
+    }
+}
+
+/// Deserialize a [`Filter`], limiting the number of values of each generic tag
+pub(crate) struct FilterVisitor {
+    pub(crate) max_values_per_tag: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for FilterVisitor {
+    type Value = Filter;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FilterVisitor {
+    type Value = Filter;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("filter object")
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut ids: Option<AllocSet<EventId>> = None;
+        let mut authors: Option<AllocSet<XOnlyPublicKey>> = None;
+        let mut kinds: Option<AllocSet<Kind>> = None;
+        let mut search: Option<Option<String>> = None;
+        let mut since: Option<Option<Timestamp>> = None;
+        let mut until: Option<Option<Timestamp>> = None;
+        let mut limit: Option<Option<usize>> = None;
+        let mut generic_tags: GenericTags = AllocMap::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "ids" => next_field(&mut map, &mut ids, "ids")?,
+                "authors" => next_field(&mut map, &mut authors, "authors")?,
+                "kinds" => next_field(&mut map, &mut kinds, "kinds")?,
+                "search" => next_field(&mut map, &mut search, "search")?,
+                "since" => next_field(&mut map, &mut since, "since")?,
+                "until" => next_field(&mut map, &mut until, "until")?,
+                "limit" => next_field(&mut map, &mut limit, "limit")?,
+                key => {
+                    next_generic_tag(&mut map, key, &mut generic_tags, self.max_values_per_tag)?;
+                }
+            }
+        }
+
+        Ok(Filter {
+            ids: ids.unwrap_or_default(),
+            authors: authors.unwrap_or_default(),
+            kinds: kinds.unwrap_or_default(),
+            search: search.flatten(),
+            since: since.flatten(),
+            until: until.flatten(),
+            limit: limit.flatten(),
+            generic_tags,
+            ..Filter::default()
+        })
+    }
+}
+
+/// Deserialize the value of a map entry, failing if the field was already set
+fn next_field<'de, M, T>(
+    map: &mut M,
+    field: &mut Option<T>,
+    name: &'static str,
+) -> Result<(), M::Error>
+where
+    M: MapAccess<'de>,
+    T: Deserialize<'de>,
+{
+    if field.is_some() {
+        return Err(serde::de::Error::duplicate_field(name));
+    }
+    *field = Some(map.next_value()?);
+    Ok(())
+}
+
+/// Deserialize the value of a `#X` map entry into `generic_tags` (other keys are ignored)
+///
+/// Values of duplicated keys are merged. Fail as soon as a tag has more than `max_values_per_tag` values.
+///
+/// Return the tag and the number of values of the entry, including the invalid (dropped) ones.
+fn next_generic_tag<'de, M>(
+    map: &mut M,
+    key: &str,
+    generic_tags: &mut GenericTags,
+    max_values_per_tag: usize,
+) -> Result<Option<(SingleLetterTag, usize)>, M::Error>
+where
+    M: MapAccess<'de>,
+{
+    let mut chars = key.chars();
+    if let (Some('#'), Some(ch), None) = (chars.next(), chars.next(), chars.next()) {
+        let tag: SingleLetterTag =
+            SingleLetterTag::from_char(ch).map_err(serde::de::Error::custom)?;
+        let values: &mut AllocSet<GenericTagValue> =
+            generic_tags.entry(tag).or_insert_with(AllocSet::new);
+        let count: usize = map.next_value_seed(GenericTagValuesSeed {
+            tag,
+            values,
+            max_values: max_values_per_tag,
+        })?;
+        Ok(Some((tag, count)))
+    } else {
+        map.next_value::<IgnoredAny>()?;
+        Ok(None)
+    }
+}
+
+/// Deserialize the values of a generic tag one by one, counting them
+struct GenericTagValuesSeed<'a> {
+    tag: SingleLetterTag,
+    values: &'a mut AllocSet<GenericTagValue>,
+    max_values: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for GenericTagValuesSeed<'_> {
+    type Value = usize;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for GenericTagValuesSeed<'_> {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("array of strings")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut len: usize = 0;
+        let mut count: usize = self.values.len();
+        while let Some(value) = seq.next_element::<String>()? {
+            len += 1;
+            count += 1;
+            if count > self.max_values {
+                return Err(serde::de::Error::custom(format!(
+                    "too many values for `#{}`: more than {}",
+                    self.tag, self.max_values
+                )));
+            }
+
+            let value: GenericTagValue = GenericTagValue::parse(self.tag, &value);
+            if is_valid_generic_tag_value(self.tag, &value) {
+                self.values.insert(value);
+            }
+        }
+        Ok(len)
+    }
+}
+
+/// Check the type of a generic tag value: `p` and `P` require a public key, `e` an event ID
+fn is_valid_generic_tag_value(tag: SingleLetterTag, value: &GenericTagValue) -> bool {
+    match tag.character {
+        Alphabet::P => matches!(value, GenericTagValue::Pubkey(_)),
+        Alphabet::E if tag.is_lowercase() => matches!(value, GenericTagValue::EventId(_)),
+        _ => true,
+    }
+}
+
+impl Filter {
+    /// Overlay a [`FilterUpdate`], overriding only the fields explicitly present in it
+    ///
+    /// An empty generic tag list in the update removes that tag from the filter.
+    pub fn apply_update(mut self, update: FilterUpdate) -> Self {
+        if let Some(ids) = update.ids {
+            self.ids = ids;
+        }
+        if let Some(authors) = update.authors {
+            self.authors = authors;
+        }
+        if let Some(kinds) = update.kinds {
+            self.kinds = kinds;
+        }
+        if let Some(search) = update.search {
+            self.search = search;
+        }
+        if let Some(since) = update.since {
+            self.since = since;
+        }
+        if let Some(until) = update.until {
+            self.until = until;
+        }
+        if let Some(limit) = update.limit {
+            self.limit = limit;
+        }
+        for (tag, values) in update.generic_tags.into_iter() {
+            if values.is_empty() {
+                self.generic_tags.remove(&tag);
+            } else {
+                self.generic_tags.insert(tag, values);
+            }
+        }
+        if let Some(exclude_authors) = update.exclude_authors {
+            self.exclude_authors = exclude_authors;
+        }
+        self
+    }
+}
+
+/// Partial [`Filter`], applied with [`Filter::apply_update`]
+///
+/// Omitted fields are `None`, so they can be told apart from the cleared ones
+/// (i.e. `"kinds": []` or `"limit": null`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterUpdate {
+    /// List of [`EventId`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub ids: Option<AllocSet<EventId>>,
+    /// List of [`XOnlyPublicKey`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub authors: Option<AllocSet<XOnlyPublicKey>>,
+    /// List of a kind numbers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub kinds: Option<AllocSet<Kind>>,
+    /// Search query (`Some(None)` clears it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub search: Option<Option<String>>,
+    /// Since timestamp (`Some(None)` clears it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub since: Option<Option<Timestamp>>,
+    /// Until timestamp (`Some(None)` clears it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub until: Option<Option<Timestamp>>,
+    /// Limit (`Some(None)` clears it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub limit: Option<Option<usize>>,
+    /// Generic tags to replace (an empty list removes the tag)
+    ///
+    /// The deserialization fails if all the values of a tag are invalid (i.e. `#p` values that aren't public keys),
+    /// so the tag isn't removed by mistake.
+    #[serde(
+        flatten,
+        serialize_with = "serialize_generic_tags",
+        deserialize_with = "deserialize_generic_tags"
+    )]
+    #[serde(default)]
+    pub generic_tags: GenericTags,
+    /// Authors to exclude when matching events locally
+    ///
+    /// Skipped by serde: the update JSON is a partial [`Filter`] JSON, which never includes
+    /// [`Filter::exclude_authors`] (so the local mute list can't leak to the relays).
+    /// Set it directly on the deserialized update instead.
+    #[serde(skip)]
+    pub exclude_authors: Option<AllocSet<XOnlyPublicKey>>,
+}
+
+impl JsonUtil for FilterUpdate {
+    type Err = serde_json::Error;
+}
+
+/// Size of a JSON string of a 32-byte hex value (64 chars + quotes)
+const HEX_STR_SIZE: usize = 64 + 2;
+
+/// Get the number of decimal digits of `n`
+fn num_digits(mut n: u64) -> usize {
+    let mut digits: usize = 1;
+    while n >= 10 {
+        n /= 10;
+        digits += 1;
+    }
+    digits
+}
+
+fn timestamp_digits(timestamp: &Timestamp) -> usize {
+    let t: i64 = timestamp.as_i64();
+    usize::from(t < 0) + num_digits(t.unsigned_abs())
+}
+
+/// Decode `npub` or `nprofile` into [`XOnlyPublicKey`]
+fn public_key_from_bech32(s: &str) -> Result<XOnlyPublicKey, Nip19Error> {
+    match Nip19::from_bech32(s)? {
+        Nip19::Pubkey(public_key) => Ok(public_key),
+        Nip19::Profile(profile) => Ok(profile.public_key),
+        _ => Err(Nip19Error::WrongPrefixOrVariant),
+    }
+}
+
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    if count == 1 {
+        format!("1 {singular}")
+    } else {
+        format!("{count} {plural}")
+    }
+}
+
+/// Get the size of an escaped JSON string (quotes included)
+fn json_str_size(s: &str) -> usize {
+    let escaped: usize = s
+        .bytes()
+        .map(|b| match b {
+            b'"' | b'\\' | b'\n' | b'\r' | b'\t' | 0x08 | 0x0c => 2,
+            0x00..=0x1f => 6,
+            _ => 1,
+        })
+        .sum();
+    escaped + 2
+}
+
+/// Get the size of a JSON array, given the size of its items
+fn json_array_size<I>(items: I) -> usize
+where
+    I: Iterator<Item = usize>,
+{
+    let mut len: usize = 0;
+    let mut size: usize = 0;
+    for item in items {
+        len += 1;
+        size += item;
+    }
+    2 + size + len.saturating_sub(1)
+}
+
+/// Deserialize a present field (also if `null`) as `Some`
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+fn serialize_generic_tags<S>(generic_tags: &GenericTags, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut map = serializer.serialize_map(Some(generic_tags.len()))?;
+    for (tag, values) in generic_tags.iter() {
+        let values: Vec<String> = GenericTagValue::sorted_by_display(values);
+        map.serialize_entry(&tag.to_string(), &values)?;
+    }
+    map.end()
+}
+
+fn deserialize_generic_tags<'de, D>(deserializer: D) -> Result<GenericTags, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct GenericTagsVisitor;
+
+    impl<'de> Visitor<'de> for GenericTagsVisitor {
+        type Value = GenericTags;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("map in which the keys are \"#X\" for some character X")
+        }
+
+        fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+        where
+            M: MapAccess<'de>,
+        {
+            let mut generic_tags: GenericTags = AllocMap::new();
+            while let Some(key) = map.next_key::<String>()? {
+                if let Some((tag, count)) =
+                    next_generic_tag(&mut map, &key, &mut generic_tags, usize::MAX)?
+                {
+                    // An empty list removes the tag from the filter: if all the values are invalid,
+                    // fail instead of broadening the filter.
+                    let is_empty: bool = generic_tags.get(&tag).map_or(true, |v| v.is_empty());
+                    if count > 0 && is_empty {
+                        return Err(serde::de::Error::custom(format!(
+                            "no valid values for `{tag}`"
+                        )));
+                    }
+                }
+            }
+            Ok(generic_tags)
+        }
+    }
+
+    deserializer.deserialize_map(GenericTagsVisitor)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_single_letter_tag_invalid_char() {
+        let err = SingleLetterTag::from_char('1').unwrap_err();
+        assert_eq!(err, SingleLetterTagError::InvalidChar('1'));
+        assert_eq!(err.to_string(), "invalid alphabet char: '1'");
+
+        let err = serde_json::from_str::<SingleLetterTag>(r#""1""#).unwrap_err();
+        assert!(err.to_string().contains("invalid alphabet char: '1'"));
+    }
+
+    #[test]
+    fn test_single_letter_tag_same_letter() {
+        let lower = SingleLetterTag::lowercase(Alphabet::P);
+        let upper = SingleLetterTag::uppercase(Alphabet::P);
+        assert!(lower.same_letter(&upper));
+        assert_ne!(lower, upper);
+        assert!(!lower.same_letter(&SingleLetterTag::lowercase(Alphabet::E)));
+    }
+
+    #[test]
+    fn test_single_letter_tag_case_conversion() {
+        let lower = SingleLetterTag::lowercase(Alphabet::P);
+        let upper = SingleLetterTag::uppercase(Alphabet::P);
+        assert_eq!(lower.to_uppercase(), upper);
+        assert_eq!(upper.to_lowercase(), lower);
+        assert_eq!(lower.to_lowercase(), lower);
+        assert_eq!(upper.to_uppercase().as_char(), 'P');
+    }
+
+    #[test]
+    fn test_subscription_id_matches() {
+        let id = SubscriptionId::new("myid");
+        assert!(id.matches("myid"));
+        assert!(id.matches("myid  "));
+        assert!(id.matches(" myid\t\n"));
+        assert!(!id.matches("otherid"));
+        assert!(!id.matches("my id"));
+
+        // Strict equality
+        assert_ne!(id, SubscriptionId::new("myid  "));
+    }
+
+    #[test]
+    fn test_kind_concatenation() {
+        let filter = Filter::new()
+            .kind(Kind::Metadata)
+            .kind(Kind::TextNote)
+            .kind(Kind::ContactList)
+            .kinds([
+                Kind::EncryptedDirectMessage,
+                Kind::Metadata,
+                Kind::LongFormTextNote,
+            ]);
+        assert_eq!(
+            filter,
+            Filter::new().kinds([
+                Kind::Metadata,
+                Kind::TextNote,
+                Kind::ContactList,
+                Kind::EncryptedDirectMessage,
+                Kind::LongFormTextNote
+            ])
+        );
+    }
+
+    #[test]
+    fn test_remove_ids() {
+        let event_id =
+            EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
+                .unwrap();
+        let filter = Filter::new().id(EventId::all_zeros()).id(event_id);
+        let filter = filter.remove_ids([EventId::all_zeros()]);
+        assert_eq!(filter, Filter::new().id(event_id));
+    }
+
+    #[test]
+    fn test_remove_custom_tag() {
+        let filter =
+            Filter::new().custom_tag(SingleLetterTag::lowercase(Alphabet::C), ["test", "test2"]);
+        let filter = filter.remove_custom_tag(SingleLetterTag::lowercase(Alphabet::C), ["test2"]);
+        assert_eq!(
+            filter,
+            Filter::new().custom_tag(SingleLetterTag::lowercase(Alphabet::C), ["test"])
+        );
+    }
+
+    #[test]
+    fn test_add_remove_event_tag() {
+        let mut filter = Filter::new().identifier("myidentifier");
+        filter = filter.custom_tag(SingleLetterTag::lowercase(Alphabet::D), ["mysecondid"]);
+        filter = filter.identifiers(["test", "test2"]);
+        filter = filter.remove_custom_tag(SingleLetterTag::lowercase(Alphabet::D), ["test2"]);
+        filter = filter.remove_identifiers(["mysecondid"]);
+        assert_eq!(filter, Filter::new().identifiers(["myidentifier", "test"]));
+    }
+
+    #[test]
     #[cfg(not(feature = "std"))]
     fn test_filter_serialization() {
         let filter = Filter::new()
@@ -870,51 +1829,773 @@ mod test {
                 SingleLetterTag::lowercase(Alphabet::P),
                 ["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"],
             );
-        let json = r##"{"search":"test","#d":["identifier"],"#j":["test1"],"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}"##;
-        assert_eq!(filter.as_json(), json.to_string());
+        let json = r##"{"search":"test","#d":["identifier"],"#j":["test1"],"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}"##;
+        assert_eq!(filter.as_json(), json.to_string());
+    }
+
+    #[test]
+    fn test_filter_serialization_with_uppercase_tag() {
+        let filter = Filter::new().custom_tag(
+            SingleLetterTag::uppercase(Alphabet::P),
+            ["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"],
+        );
+        let json =
+            r##"{"#P":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}"##;
+        assert_eq!(filter.as_json(), json);
+    }
+
+    #[test]
+    fn test_filter_deserialization() {
+        let json = r##"{"#a":["...", "test"],"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"],"search":"test","ids":["70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5"]}"##;
+        let filter = Filter::from_json(json).unwrap();
+        let event_id =
+            EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
+                .unwrap();
+        let pubkey = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        assert_eq!(
+            filter,
+            Filter::new()
+                .ids([event_id])
+                .search("test")
+                .custom_tag(
+                    SingleLetterTag::lowercase(Alphabet::A),
+                    ["...".to_string(), "test".to_string()]
+                )
+                .pubkey(pubkey)
+        );
+
+        let json = r##"{"#":["..."],"search":"test"}"##;
+        let filter = Filter::from_json(json).unwrap();
+        assert_eq!(filter, Filter::new().search("test"));
+
+        let json = r##"{"aa":["..."],"search":"test"}"##;
+        let filter = Filter::from_json(json).unwrap();
+        assert_eq!(filter, Filter::new().search("test"));
+    }
+
+    #[test]
+    fn test_filter_deserialization_stringified_timestamps() {
+        let filter = Filter::from_json(r#"{"since":"1700000000","until":1700000100}"#).unwrap();
+        assert_eq!(filter.since, Some(Timestamp::from(1700000000)));
+        assert_eq!(filter.until, Some(Timestamp::from(1700000100)));
+        assert_eq!(
+            filter.as_json(),
+            r#"{"since":1700000000,"until":1700000100}"#
+        );
+    }
+
+    #[test]
+    fn test_filter_difference_window() {
+        let covered = Filter::new()
+            .kind(Kind::TextNote)
+            .since(Timestamp::from(100))
+            .until(Timestamp::from(200));
+
+        // Subset
+        let filter = Filter::new()
+            .kind(Kind::TextNote)
+            .since(Timestamp::from(120))
+            .until(Timestamp::from(180));
+        assert!(filter.difference(&covered).is_empty());
+        assert!(covered.difference(&covered).is_empty());
+
+        // Window widened on both sides
+        let filter = Filter::new()
+            .kind(Kind::TextNote)
+            .since(Timestamp::from(50))
+            .until(Timestamp::from(250));
+        assert_eq!(
+            filter.difference(&covered),
+            vec![
+                Filter::new()
+                    .kind(Kind::TextNote)
+                    .since(Timestamp::from(50))
+                    .until(Timestamp::from(99)),
+                Filter::new()
+                    .kind(Kind::TextNote)
+                    .since(Timestamp::from(201))
+                    .until(Timestamp::from(250)),
+            ]
+        );
+
+        // Window widened to the past only
+        let filter = Filter::new()
+            .kind(Kind::TextNote)
+            .until(Timestamp::from(200));
+        assert_eq!(
+            filter.difference(&covered),
+            vec![Filter::new()
+                .kind(Kind::TextNote)
+                .until(Timestamp::from(99))]
+        );
+    }
+
+    #[test]
+    fn test_filter_difference_authors() {
+        let pk1 = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let pk2 = XOnlyPublicKey::from_str(
+            "68d81165918100b7da43fc28f7d1fc12554466e1115886b9e7bb326f65ec4272",
+        )
+        .unwrap();
+
+        let covered = Filter::new().kind(Kind::TextNote).author(pk1);
+        let filter = Filter::new().kind(Kind::TextNote).authors([pk1, pk2]);
+        assert_eq!(
+            filter.difference(&covered),
+            vec![Filter::new().kind(Kind::TextNote).author(pk2)]
+        );
+        assert!(covered.difference(&filter).is_empty());
+
+        // Not computable: return `self`
+        let filter = Filter::new().kind(Kind::Metadata).authors([pk1, pk2]);
+        assert_eq!(filter.difference(&covered), vec![filter.clone()]);
+    }
+
+    #[test]
+    fn test_filter_difference_limit() {
+        let pk1 = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let pk2 = XOnlyPublicKey::from_str(
+            "68d81165918100b7da43fc28f7d1fc12554466e1115886b9e7bb326f65ec4272",
+        )
+        .unwrap();
+
+        // The covered filter may not have returned all the events
+        let covered = Filter::new().kind(Kind::TextNote).author(pk1).limit(10);
+        let filter = Filter::new().kind(Kind::TextNote).author(pk1);
+        assert_eq!(filter.difference(&covered), vec![filter.clone()]);
+
+        let covered = Filter::new()
+            .kind(Kind::TextNote)
+            .since(Timestamp::from(100))
+            .limit(10);
+        let filter = Filter::new()
+            .kind(Kind::TextNote)
+            .since(Timestamp::from(50))
+            .limit(10);
+        assert_eq!(filter.difference(&covered), vec![filter.clone()]);
+
+        // Limited `self`
+        let covered = Filter::new().kind(Kind::TextNote).author(pk1);
+        let filter = Filter::new()
+            .kind(Kind::TextNote)
+            .authors([pk1, pk2])
+            .limit(10);
+        assert_eq!(filter.difference(&covered), vec![filter.clone()]);
+
+        // Same filter
+        assert!(covered
+            .clone()
+            .limit(10)
+            .difference(&covered.limit(10))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_filter_authors_and_pubkeys_into_xonly() {
+        struct PublicKey(XOnlyPublicKey);
+
+        impl From<PublicKey> for XOnlyPublicKey {
+            fn from(pk: PublicKey) -> Self {
+                pk.0
+            }
+        }
+
+        let pk = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Filter::new().author(pk),
+            Filter::new().author(PublicKey(pk))
+        );
+        assert_eq!(
+            Filter::new().authors([pk]),
+            Filter::new().authors([PublicKey(pk)])
+        );
+        assert_eq!(
+            Filter::new().pubkey(pk),
+            Filter::new().pubkey(PublicKey(pk))
+        );
+        assert_eq!(
+            Filter::new().pubkeys([pk]),
+            Filter::new().pubkeys([PublicKey(pk)])
+        );
+    }
+
+    #[test]
+    fn test_filter_estimated_size() {
+        let pk = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let event_id =
+            EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
+                .unwrap();
+
+        let filters = vec![
+            Filter::new(),
+            Filter::new().kind(Kind::TextNote),
+            Filter::new()
+                .id(event_id)
+                .author(pk)
+                .kinds([Kind::Metadata, Kind::Custom(12345), Kind::LongFormTextNote])
+                .since(Timestamp::from(1700000000))
+                .until(Timestamp::from(1710000000))
+                .limit(100),
+            Filter::new()
+                .search("best \"nostr\" apps\n")
+                .hashtags(["bitcoin", "nostr"])
+                .pubkey(pk)
+                .event(event_id),
+            Filter::new()
+                .custom_tag(SingleLetterTag::uppercase(Alphabet::P), [pk])
+                .identifier("")
+                .limit(0),
+        ];
+
+        for filter in filters.into_iter() {
+            let estimated: usize = filter.estimated_size();
+            let exact: usize = filter.as_json().len();
+            assert!(
+                estimated.abs_diff(exact) <= 2,
+                "estimated={estimated}, exact={exact}"
+            );
+        }
     }
 
     #[test]
-    fn test_filter_serialization_with_uppercase_tag() {
-        let filter = Filter::new().custom_tag(
-            SingleLetterTag::uppercase(Alphabet::P),
-            ["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"],
+    fn test_filter_to_coordinates() {
+        let pk1 = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let pk2 = XOnlyPublicKey::from_str(
+            "68d81165918100b7da43fc28f7d1fc12554466e1115886b9e7bb326f65ec4272",
+        )
+        .unwrap();
+
+        let filter = Filter::new()
+            .authors([pk1, pk2])
+            .kind(Kind::LongFormTextNote)
+            .identifier("my-article");
+        let mut coordinates = filter.to_coordinates();
+        coordinates.sort();
+
+        let mut expected = vec![
+            Coordinate::new(Kind::LongFormTextNote, pk1).identifier("my-article"),
+            Coordinate::new(Kind::LongFormTextNote, pk2).identifier("my-article"),
+        ];
+        expected.sort();
+        assert_eq!(coordinates, expected);
+
+        // Not parameterized replaceable kind
+        let filter = Filter::new()
+            .authors([pk1, pk2])
+            .kind(Kind::TextNote)
+            .identifier("my-article");
+        assert!(filter.to_coordinates().is_empty());
+
+        // No authors
+        let filter = Filter::new()
+            .kind(Kind::LongFormTextNote)
+            .identifier("my-article");
+        assert!(filter.to_coordinates().is_empty());
+    }
+
+    #[test]
+    fn test_filter_deserialization_duplicated_tags() {
+        let json = r##"{"#t":["bitcoin","nostr"],"kinds":[1],"#t":["nostr","rust"]}"##;
+        let filter = Filter::from_json(json).unwrap();
+        assert_eq!(
+            filter,
+            Filter::new()
+                .kind(Kind::TextNote)
+                .hashtags(["bitcoin", "nostr", "rust"])
+        );
+    }
+
+    #[test]
+    fn test_filter_clamp_limit() {
+        // Clamp a high limit
+        let filter = Filter::new().limit(1000).clamp_limit(500);
+        assert_eq!(filter.limit, Some(500));
+
+        // Set a limit where none existed
+        let filter = Filter::new().clamp_limit(500);
+        assert_eq!(filter.limit, Some(500));
+
+        // Leave a lower limit untouched
+        let filter = Filter::new().limit(10).clamp_limit(500);
+        assert_eq!(filter.limit, Some(10));
+    }
+
+    #[test]
+    fn test_filter_clamp_filters() {
+        let filters = vec![
+            Filter::new().kind(Kind::TextNote).limit(1000),
+            Filter::new().kind(Kind::Metadata),
+            Filter::new().kind(Kind::ContactList).limit(5),
+        ];
+        let filters = Filter::clamp_filters(filters, 100, 2);
+        assert_eq!(
+            filters,
+            vec![
+                Filter::new().kind(Kind::TextNote).limit(100),
+                Filter::new().kind(Kind::Metadata).limit(100),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_kind_from_u16() {
+        let filter = Filter::new().kind(30023u16).kinds([7u16, 4321u16]);
+        assert_eq!(
+            filter,
+            Filter::new().kinds([Kind::LongFormTextNote, Kind::Reaction, Kind::Custom(4321)])
+        );
+    }
+
+    #[test]
+    fn test_generic_tag_value_parse() {
+        let pubkey_hex = "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe";
+        let pubkey = XOnlyPublicKey::from_str(pubkey_hex).unwrap();
+        let event_id_hex = "70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5";
+        let event_id = EventId::from_hex(event_id_hex).unwrap();
+
+        // p
+        let tag = SingleLetterTag::lowercase(Alphabet::P);
+        assert_eq!(
+            GenericTagValue::parse(tag, pubkey_hex),
+            GenericTagValue::Pubkey(pubkey)
+        );
+        assert_eq!(
+            GenericTagValue::parse(tag, "invalid"),
+            GenericTagValue::String(String::from("invalid"))
+        );
+
+        // P
+        let tag = SingleLetterTag::uppercase(Alphabet::P);
+        assert_eq!(
+            GenericTagValue::parse(tag, pubkey_hex),
+            GenericTagValue::Pubkey(pubkey)
+        );
+
+        // e
+        let tag = SingleLetterTag::lowercase(Alphabet::E);
+        assert_eq!(
+            GenericTagValue::parse(tag, event_id_hex),
+            GenericTagValue::EventId(event_id)
+        );
+        assert_eq!(
+            GenericTagValue::parse(tag, pubkey_hex),
+            GenericTagValue::EventId(EventId::from_hex(pubkey_hex).unwrap())
+        );
+        assert_eq!(
+            GenericTagValue::parse(tag, "invalid"),
+            GenericTagValue::String(String::from("invalid"))
+        );
+
+        // E
+        let tag = SingleLetterTag::uppercase(Alphabet::E);
+        assert_eq!(
+            GenericTagValue::parse(tag, event_id_hex),
+            GenericTagValue::String(event_id_hex.to_string())
         );
+
+        // Other letters
+        let tag = SingleLetterTag::lowercase(Alphabet::T);
+        assert_eq!(
+            GenericTagValue::parse(tag, pubkey_hex),
+            GenericTagValue::String(pubkey_hex.to_string())
+        );
+    }
+
+    #[test]
+    fn test_custom_tag_coercion() {
+        let pubkey_hex = "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe";
+        let pubkey = XOnlyPublicKey::from_str(pubkey_hex).unwrap();
+
+        let filter =
+            Filter::new().custom_tag(SingleLetterTag::lowercase(Alphabet::P), [pubkey_hex]);
+        assert_eq!(filter, Filter::new().pubkey(pubkey));
+
         let json =
-            r##"{"#P":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}"##;
-        assert_eq!(filter.as_json(), json);
+            r##"{"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}"##;
+        assert_eq!(Filter::from_json(json).unwrap(), filter);
+
+        let tag = SingleLetterTag::lowercase(Alphabet::P);
+        let filter = filter.remove_custom_tag(tag, [pubkey_hex]);
+        assert!(filter.generic_tags.get(&tag).unwrap().is_empty());
     }
 
     #[test]
-    fn test_filter_deserialization() {
-        let json = r##"{"#a":["...", "test"],"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"],"search":"test","ids":["70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5"]}"##;
-        let filter = Filter::from_json(json).unwrap();
+    fn test_filter_describe() {
+        let pubkey_a = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let pubkey_b = XOnlyPublicKey::from_str(
+            "68d81165918100b7da43fc28f7d1fc12554466e1115886b9e7bb326f65ec4272",
+        )
+        .unwrap();
+        let filter = Filter::new()
+            .kind(Kind::TextNote)
+            .authors([pubkey_a, pubkey_b])
+            .hashtags(["nostr", "bitcoin"])
+            .since(Timestamp::from(1704067200))
+            .limit(10);
+        let description = filter.describe();
+        assert!(description.starts_with("events of kind 1 by 2 authors"));
+        assert!(description.contains("tagged #t=[bitcoin,nostr]"));
+        assert!(description.contains("since 2024-01-01"));
+        assert!(description.contains("limited to 10"));
+
+        assert_eq!(Filter::new().describe(), "all events");
+    }
+
+    #[test]
+    fn test_generic_tag_value_display_order() {
+        let pubkey = XOnlyPublicKey::from_str(
+            "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4",
+        )
+        .unwrap();
         let event_id =
             EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
                 .unwrap();
+        let values = [
+            GenericTagValue::String(String::from("zzz")),
+            GenericTagValue::Pubkey(pubkey),
+            GenericTagValue::String(String::from("0-string")),
+            GenericTagValue::EventId(event_id),
+        ];
+        assert_eq!(
+            GenericTagValue::sorted_by_display(values.iter()),
+            vec![
+                String::from("0-string"),
+                event_id.to_hex(),
+                pubkey.to_string(),
+                String::from("zzz"),
+            ]
+        );
+
+        // Canonical serialization
+        let filter = Filter::new().custom_tag(
+            SingleLetterTag::lowercase(Alphabet::P),
+            [
+                "zzz",
+                "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4",
+                "0-string",
+            ],
+        );
+        assert_eq!(
+            filter.as_json(),
+            r##"{"#p":["0-string","aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4","zzz"]}"##
+        );
+    }
+
+    #[test]
+    fn test_filter_authors_bech32() {
+        use crate::nips::nip19::{Nip19Profile, ToBech32};
+
+        let pubkey = XOnlyPublicKey::from_str(
+            "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4",
+        )
+        .unwrap();
+
+        let filter = Filter::new()
+            .author_bech32("npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy")
+            .unwrap();
+        assert_eq!(filter, Filter::new().author(pubkey));
+
+        let nprofile = Nip19Profile::new(pubkey, ["wss://relay.damus.io"])
+            .to_bech32()
+            .unwrap();
+        let filter = Filter::new().authors_bech32([nprofile]).unwrap();
+        assert_eq!(filter, Filter::new().author(pubkey));
+
+        assert_eq!(
+            Filter::new()
+                .author_bech32("note1m99r7nwc0wdrkzldrqan96gklg5usqspq7z9696j6unf0ljnpxjspqfw99")
+                .unwrap_err(),
+            Nip19Error::WrongPrefixOrVariant
+        );
+        assert!(Filter::new().author_bech32("npub1invalid").is_err());
+    }
+
+    #[test]
+    fn test_filter_with_hints() {
+        let id1 = EventId::all_zeros();
+        let id2 =
+            EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
+                .unwrap();
+        let url = UncheckedUrl::from("wss://relay.damus.io");
+        let filter = Filter::new().events_with_hints(vec![(id1, Some(url.clone())), (id2, None)]);
+        let values = filter
+            .generic_tags
+            .get(&SingleLetterTag::lowercase(Alphabet::E))
+            .unwrap();
+        assert!(values.contains(&GenericTagValue::EventId(id1)));
+        assert!(values.contains(&GenericTagValue::EventId(id2)));
+        assert_eq!(filter, Filter::new().events([id1, id2]));
+
         let pubkey = XOnlyPublicKey::from_str(
             "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
         )
         .unwrap();
+        let filter = Filter::new().pubkeys_with_hints([(pubkey, Some(url))]);
+        assert_eq!(filter, Filter::new().pubkey(pubkey));
+    }
+
+    #[test]
+    fn test_filter_mentions() {
+        let pubkey = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let filter = Filter::new().mentions(
+            pubkey,
+            [
+                Kind::TextNote,
+                Kind::Repost,
+                Kind::Reaction,
+                Kind::ZapReceipt,
+            ],
+        );
+        assert_eq!(
+            filter
+                .generic_tags
+                .get(&SingleLetterTag::lowercase(Alphabet::P)),
+            Some(&AllocSet::from([GenericTagValue::Pubkey(pubkey)]))
+        );
+        assert_eq!(filter.kinds.len(), 4);
+        assert!(filter.kinds.contains(&Kind::ZapReceipt));
         assert_eq!(
             filter,
             Filter::new()
-                .ids([event_id])
-                .search("test")
-                .custom_tag(
-                    SingleLetterTag::lowercase(Alphabet::A),
-                    ["...".to_string(), "test".to_string()]
-                )
-                .pubkey(pubkey)
+                .custom_tag(SingleLetterTag::lowercase(Alphabet::P), [pubkey])
+                .kinds([
+                    Kind::TextNote,
+                    Kind::Repost,
+                    Kind::Reaction,
+                    Kind::ZapReceipt
+                ])
         );
+    }
 
-        let json = r##"{"#":["..."],"search":"test"}"##;
-        let filter = Filter::from_json(json).unwrap();
-        assert_eq!(filter, Filter::new().search("test"));
+    #[test]
+    fn test_filter_pagination() {
+        let filter = Filter::new()
+            .limit(20)
+            .page_before(Timestamp::from(1700000000));
+        assert_eq!(filter.until, Some(Timestamp::from(1700000000)));
+        assert_eq!(filter.since, None);
+        assert_eq!(filter.limit, Some(20));
 
-        let json = r##"{"aa":["..."],"search":"test"}"##;
-        let filter = Filter::from_json(json).unwrap();
-        assert_eq!(filter, Filter::new().search("test"));
+        // Keep existing `since`
+        let filter = Filter::new()
+            .since(Timestamp::from(1600000000))
+            .page_before(Timestamp::from(1700000000));
+        assert_eq!(filter.since, Some(Timestamp::from(1600000000)));
+        assert_eq!(filter.until, Some(Timestamp::from(1700000000)));
+
+        // Empty window: not swapped
+        let filter = Filter::new()
+            .since(Timestamp::from(1700000000))
+            .page_before(Timestamp::from(1600000000));
+        assert_eq!(filter.since, Some(Timestamp::from(1700000000)));
+        assert_eq!(filter.until, Some(Timestamp::from(1600000000)));
+
+        let filter = Filter::new()
+            .until(Timestamp::from(1700000000))
+            .page_after(Timestamp::from(1600000000));
+        assert_eq!(filter.since, Some(Timestamp::from(1600000000)));
+        assert_eq!(filter.until, Some(Timestamp::from(1700000000)));
+    }
+
+    #[test]
+    fn test_filter_search_terms() {
+        let filter = Filter::new().search_terms(["nostr", "rust sdk", "bitcoin"]);
+        assert_eq!(filter.search, Some(String::from("nostr rust sdk bitcoin")));
+
+        let filter = filter.add_search_term("lightning");
+        assert_eq!(
+            filter.search,
+            Some(String::from("nostr rust sdk bitcoin lightning"))
+        );
+
+        let filter = Filter::new().add_search_term("nostr");
+        assert_eq!(filter.search, Some(String::from("nostr")));
+
+        let filter = Filter::new().search_terms(Vec::<String>::new());
+        assert!(filter.search.is_none());
+    }
+
+    #[test]
+    fn test_filter_kinds_range() {
+        let filter = Filter::new().kinds_range(30000, 30003);
+        assert_eq!(
+            filter,
+            Filter::new().kinds([
+                Kind::CategorizedPeopleList,
+                Kind::CategorizedBookmarkList,
+                Kind::ParameterizedReplaceable(30002),
+                Kind::ParameterizedReplaceable(30003),
+            ])
+        );
+
+        // Empty range
+        let filter = Filter::new().kinds_range(10, 5);
+        assert!(filter.kinds.is_empty());
+
+        // Cap
+        let filter = Filter::new().kinds_range(0, u16::MAX);
+        assert_eq!(filter.kinds.len(), MAX_KINDS_RANGE_LEN);
+        assert!(filter.kinds.contains(&Kind::Metadata));
+        assert!(!filter.kinds.contains(&Kind::from(1000u16)));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_filter_bulk_authors() {
+        let authors: Vec<XOnlyPublicKey> = (0..10_000)
+            .map(|_| crate::Keys::generate().public_key())
+            .collect();
+        let filter = Filter::new().authors(authors.clone());
+        assert_eq!(filter.authors.len(), 10_000);
+        assert!(filter.authors.capacity() >= 10_000);
+        assert!(authors.iter().all(|a| filter.authors.contains(a)));
+    }
+
+    #[test]
+    fn test_filter_split_by_kind() {
+        let pubkey = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let filter = Filter::new()
+            .kinds([Kind::TextNote, Kind::Repost, Kind::Reaction])
+            .author(pubkey)
+            .limit(10);
+        let filters = filter.split_by_kind();
+        assert_eq!(
+            filters,
+            vec![
+                Filter::new().kind(Kind::TextNote).author(pubkey).limit(10),
+                Filter::new().kind(Kind::Repost).author(pubkey).limit(10),
+                Filter::new().kind(Kind::Reaction).author(pubkey).limit(10),
+            ]
+        );
+
+        // Empty kinds
+        let filter = Filter::new().author(pubkey);
+        assert_eq!(filter.split_by_kind(), vec![filter]);
+    }
+
+    #[test]
+    fn test_filter_from_json_bounded() {
+        let pubkey: &str = "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe";
+        let values: Vec<&str> = vec![pubkey; 11];
+        let json: String = format!(r##"{{"kinds":[1],"#p":{}}}"##, serde_json::json!(values));
+
+        let err = Filter::from_json_bounded(&json, 10).unwrap_err();
+        assert!(err.to_string().contains("too many values for `#p`"));
+
+        let filter = Filter::from_json_bounded(&json, DEFAULT_MAX_GENERIC_TAG_VALUES).unwrap();
+        assert_eq!(filter, Filter::from_json(&json).unwrap());
+
+        // Values of duplicated keys are merged, and counted together
+        let json = r##"{"#t":["a","b"],"#t":["c"]}"##;
+        let filter = Filter::from_json_bounded(json, 3).unwrap();
+        assert_eq!(filter, Filter::new().hashtags(["a", "b", "c"]));
+        assert!(Filter::from_json_bounded(json, 2).is_err());
+    }
+
+    #[test]
+    fn test_filter_apply_update() {
+        let pubkey = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let filter = Filter::new()
+            .author(pubkey)
+            .kinds([Kind::TextNote, Kind::Repost])
+            .limit(10)
+            .since(Timestamp::from(1000));
+
+        let update = FilterUpdate::from_json(r#"{"limit":50}"#).unwrap();
+        let updated = filter.clone().apply_update(update);
+        assert_eq!(updated.limit, Some(50));
+        assert_eq!(updated.kinds, filter.kinds);
+        assert_eq!(updated.authors, filter.authors);
+        assert_eq!(updated.since, filter.since);
+
+        // Explicit `null` and empty lists clear the fields
+        let update = FilterUpdate::from_json(r#"{"kinds":[],"since":null}"#).unwrap();
+        let updated = filter.clone().apply_update(update);
+        assert!(updated.kinds.is_empty());
+        assert_eq!(updated.since, None);
+        assert_eq!(updated.limit, Some(10));
+        assert_eq!(updated.authors, filter.authors);
+    }
+
+    #[test]
+    fn test_filter_update_invalid_generic_tag_values() {
+        let pubkey = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let filter = Filter::new().kind(Kind::TextNote).pubkey(pubkey);
+
+        // All invalid: fail instead of removing the tag
+        let err = FilterUpdate::from_json(r##"{"#p":["invalid"]}"##).unwrap_err();
+        assert!(err.to_string().contains("no valid values for `#p`"));
+        assert!(FilterUpdate::from_json(r##"{"#e":["invalid","other"]}"##).is_err());
+
+        // Invalid values are dropped if at least one is valid
+        let update =
+            FilterUpdate::from_json(format!(r##"{{"#p":["invalid","{pubkey}"]}}"##)).unwrap();
+        let updated = filter.clone().apply_update(update);
+        assert_eq!(updated, filter);
+
+        // An explicitly empty list still removes the tag
+        let update = FilterUpdate::from_json(r##"{"#p":[]}"##).unwrap();
+        let updated = filter.apply_update(update);
+        assert_eq!(updated, Filter::new().kind(Kind::TextNote));
+    }
+
+    #[test]
+    fn test_filter_is_satisfiable() {
+        let filter = Filter::new()
+            .since(Timestamp::from(200))
+            .until(Timestamp::from(100));
+        assert!(!filter.is_satisfiable());
+
+        let filter = Filter::new()
+            .since(Timestamp::from(100))
+            .until(Timestamp::from(200));
+        assert!(filter.is_satisfiable());
+
+        // Same timestamp: the window is inclusive
+        let filter = Filter::new()
+            .since(Timestamp::from(100))
+            .until(Timestamp::from(100));
+        assert!(filter.is_satisfiable());
+
+        // Relays commonly return nothing for `limit: 0`
+        let filter = Filter::new().kind(Kind::TextNote).limit(0);
+        assert!(!filter.is_satisfiable());
+
+        assert!(Filter::new().is_satisfiable());
     }
 
     #[test]
@@ -925,4 +2606,24 @@ mod test {
         let filter = Filter::new();
         assert!(filter.is_empty());
     }
+
+    #[test]
+    fn test_filter_exclude_authors_local_only() {
+        let pubkey = XOnlyPublicKey::from_str(
+            "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a",
+        )
+        .unwrap();
+
+        let filter = Filter::new().exclude_authors([pubkey]);
+        assert!(filter.is_empty());
+        assert_eq!(filter.as_json(), "{}");
+
+        // Included in the equality
+        let filter = filter.kind(Kind::TextNote);
+        assert_ne!(filter, Filter::new().kind(Kind::TextNote));
+        assert_eq!(
+            filter,
+            Filter::new().kind(Kind::TextNote).exclude_authors([pubkey])
+        );
+    }
 }
@@ -16,7 +16,7 @@ use core::str::FromStr;
 use bitcoin::secp256k1::{self, XOnlyPublicKey};
 
 use crate::event::id;
-use crate::{Filter, Kind, Tag, UncheckedUrl};
+use crate::{Filter, Kind, Tag, UncheckedUrl, Url};
 
 /// Raw Event error
 #[derive(Debug)]
@@ -29,6 +29,12 @@ pub enum Error {
     ParseInt(ParseIntError),
     /// Invalid coordinate
     InvalidCoordinate,
+    /// Coordinate kind is neither replaceable nor parameterized replaceable
+    NonReplaceableKind(Kind),
+    /// Missing identifier for parameterized replaceable kind
+    MissingIdentifier,
+    /// Unexpected identifier for replaceable kind
+    UnexpectedIdentifier,
 }
 
 #[cfg(feature = "std")]
@@ -41,6 +47,16 @@ impl fmt::Display for Error {
             Self::EventId(e) => write!(f, "Event ID: {e}"),
             Self::ParseInt(e) => write!(f, "Parse Int: {e}"),
             Self::InvalidCoordinate => write!(f, "Invalid coordinate"),
+            Self::NonReplaceableKind(kind) => write!(
+                f,
+                "Kind {kind} is neither replaceable nor parameterized replaceable"
+            ),
+            Self::MissingIdentifier => {
+                write!(f, "Missing identifier for parameterized replaceable kind")
+            }
+            Self::UnexpectedIdentifier => {
+                write!(f, "Unexpected identifier for replaceable kind")
+            }
         }
     }
 }
@@ -100,6 +116,44 @@ impl Coordinate {
         self.identifier = identifier.into();
         self
     }
+
+    /// Check if the coordinate is valid
+    ///
+    /// Parameterized replaceable kinds require a non-empty identifier,
+    /// replaceable kinds require an empty one. Other kinds are rejected.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.kind.is_parameterized_replaceable() {
+            if self.identifier.is_empty() {
+                return Err(Error::MissingIdentifier);
+            }
+        } else if self.kind.is_replaceable() {
+            if !self.identifier.is_empty() {
+                return Err(Error::UnexpectedIdentifier);
+            }
+        } else {
+            return Err(Error::NonReplaceableKind(self.kind));
+        }
+
+        Ok(())
+    }
+
+    /// Convert into a [`Filter`], keeping the parsed relay hints
+    ///
+    /// Invalid relay URLs are skipped (with a warning).
+    pub fn into_filter_with_relays(self) -> (Filter, Vec<Url>) {
+        let relays: Vec<Url> = self
+            .relays
+            .iter()
+            .filter_map(|relay| match Url::parse(relay) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    tracing::warn!("Skipping invalid relay url '{relay}' of coordinate: {e}");
+                    None
+                }
+            })
+            .collect();
+        (Filter::from(self), relays)
+    }
 }
 
 impl From<Coordinate> for Tag {
@@ -145,3 +199,67 @@ impl FromStr for Coordinate {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coordinate_into_filter_with_relays() {
+        let pubkey = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let mut coordinate = Coordinate::new(Kind::LongFormTextNote, pubkey).identifier("ipsum");
+        coordinate.relays = vec![
+            String::from("wss://relay.damus.io"),
+            String::from("not a url"),
+            String::from("wss://nos.lol"),
+        ];
+
+        let (filter, relays) = coordinate.clone().into_filter_with_relays();
+        assert_eq!(filter, Filter::from(coordinate));
+        assert_eq!(
+            relays,
+            vec![
+                Url::parse("wss://relay.damus.io").unwrap(),
+                Url::parse("wss://nos.lol").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coordinate_validate() {
+        let pubkey = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+
+        // Parameterized replaceable
+        let coordinate = Coordinate::new(Kind::LongFormTextNote, pubkey);
+        assert!(matches!(
+            coordinate.validate(),
+            Err(Error::MissingIdentifier)
+        ));
+        assert!(coordinate.identifier("my-article").validate().is_ok());
+
+        // Replaceable
+        let coordinate = Coordinate::new(Kind::MuteList, pubkey);
+        assert!(coordinate.validate().is_ok());
+        assert!(matches!(
+            coordinate.identifier("test").validate(),
+            Err(Error::UnexpectedIdentifier)
+        ));
+
+        // Not replaceable
+        let coordinate = Coordinate::new(Kind::TextNote, pubkey);
+        assert!(matches!(
+            coordinate.clone().validate(),
+            Err(Error::NonReplaceableKind(Kind::TextNote))
+        ));
+        assert!(matches!(
+            coordinate.identifier("test").validate(),
+            Err(Error::NonReplaceableKind(Kind::TextNote))
+        ));
+    }
+}
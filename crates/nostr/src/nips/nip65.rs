@@ -7,8 +7,15 @@
 //! <https://github.com/nostr-protocol/nips/blob/master/65.md>
 
 use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "std")]
+use bitcoin::secp256k1::XOnlyPublicKey;
 
 use crate::{Event, RelayMetadata, Tag, UncheckedUrl};
+#[cfg(feature = "std")]
+use crate::{Filter, Url};
 
 /// Extracts the relay info (url, optional read/write flag) from the event
 pub fn extract_relay_list(event: &Event) -> Vec<(UncheckedUrl, Option<RelayMetadata>)> {
@@ -23,3 +30,71 @@ pub fn extract_relay_list(event: &Event) -> Vec<(UncheckedUrl, Option<RelayMetad
         })
         .collect()
 }
+
+/// Build per-relay filters for the outbox model
+///
+/// Given the write relays of each author and a base [`Filter`], produce a filter for every relay,
+/// with the `authors` constrained to only those who publish to that relay.
+/// The `authors` of the base [`Filter`] are replaced.
+#[cfg(feature = "std")]
+pub fn outbox_filters(
+    write_relays: HashMap<XOnlyPublicKey, Vec<Url>>,
+    base: Filter,
+) -> HashMap<Url, Filter> {
+    let mut authors_by_relay: HashMap<Url, HashSet<XOnlyPublicKey>> = HashMap::new();
+
+    for (author, relays) in write_relays.into_iter() {
+        for url in relays.into_iter() {
+            authors_by_relay.entry(url).or_default().insert(author);
+        }
+    }
+
+    authors_by_relay
+        .into_iter()
+        .map(|(url, authors)| {
+            let mut filter: Filter = base.clone();
+            filter.authors = authors;
+            (url, filter)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod tests {
+    use core::str::FromStr;
+
+    use super::*;
+    use crate::Kind;
+
+    #[test]
+    fn test_outbox_filters() {
+        let author_a = XOnlyPublicKey::from_str(
+            "379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe",
+        )
+        .unwrap();
+        let author_b = XOnlyPublicKey::from_str(
+            "68d81165918100b7da43fc28f7d1fc12554466e1115886b9e7bb326f65ec4272",
+        )
+        .unwrap();
+
+        let shared = Url::parse("wss://relay.damus.io").unwrap();
+        let relay_a = Url::parse("wss://nos.lol").unwrap();
+        let relay_b = Url::parse("wss://nostr.wine").unwrap();
+
+        let mut write_relays: HashMap<XOnlyPublicKey, Vec<Url>> = HashMap::new();
+        write_relays.insert(author_a, vec![shared.clone(), relay_a.clone()]);
+        write_relays.insert(author_b, vec![shared.clone(), relay_b.clone()]);
+
+        let base = Filter::new().kind(Kind::TextNote).limit(10);
+        let filters = outbox_filters(write_relays, base.clone());
+
+        assert_eq!(filters.len(), 3);
+        assert_eq!(
+            filters.get(&shared),
+            Some(&base.clone().authors([author_a, author_b]))
+        );
+        assert_eq!(filters.get(&relay_a), Some(&base.clone().author(author_a)));
+        assert_eq!(filters.get(&relay_b), Some(&base.author(author_b)));
+    }
+}
@@ -14,6 +14,8 @@ use core::time::Duration;
 #[cfg(feature = "std")]
 use bitcoin::secp256k1::rand::rngs::OsRng;
 use bitcoin::secp256k1::rand::Rng;
+use serde::de::{Error, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 mod supplier;
 
@@ -22,7 +24,9 @@ pub use self::supplier::TimeSupplier;
 pub use self::supplier::{Instant, SystemTime, UNIX_EPOCH};
 
 /// Unix timestamp in seconds
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+///
+/// Serialized as a JSON number. Deserialization accepts also a numeric string (i.e. `"1700000000"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Timestamp(i64);
 
 impl Timestamp {
@@ -265,6 +269,78 @@ impl Sub<i64> for Timestamp {
     }
 }
 
+impl Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Numeric strings are accepted only by the human-readable (self-describing) formats, like JSON:
+        // the compact ones can't be asked for "any" type, so request the `i64` written by `serialize`.
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(TimestampVisitor {
+                human_readable: true,
+            })
+        } else {
+            deserializer.deserialize_i64(TimestampVisitor {
+                human_readable: false,
+            })
+        }
+    }
+}
+
+struct TimestampVisitor {
+    human_readable: bool,
+}
+
+impl Visitor<'_> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.human_readable {
+            write!(f, "an unsigned number or a numeric string")
+        } else {
+            write!(f, "an unsigned number")
+        }
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Timestamp, E>
+    where
+        E: Error,
+    {
+        let secs: i64 =
+            i64::try_from(v).map_err(|_| E::custom(format_args!("timestamp out of range: {v}")))?;
+        Ok(Timestamp(secs))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Timestamp, E>
+    where
+        E: Error,
+    {
+        Ok(Timestamp(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Timestamp, E>
+    where
+        E: Error,
+    {
+        if !self.human_readable {
+            return Err(E::invalid_type(Unexpected::Str(v), &self));
+        }
+
+        let timestamp: u64 = u64::from_str(v).map_err(E::custom)?;
+        self.visit_u64(timestamp)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,4 +353,30 @@ mod tests {
             String::from("2023-04-21T07:04:45Z")
         );
     }
+
+    #[test]
+    fn test_timestamp_serde() {
+        let timestamp: Timestamp = serde_json::from_str("1700000000").unwrap();
+        assert_eq!(timestamp, Timestamp::from(1700000000));
+
+        let timestamp: Timestamp = serde_json::from_str(r#""1700000000""#).unwrap();
+        assert_eq!(timestamp, Timestamp::from(1700000000));
+
+        assert!(serde_json::from_str::<Timestamp>(r#""abc""#).is_err());
+        assert!(serde_json::from_str::<Timestamp>(r#""-1""#).is_err());
+
+        // Out of range
+        assert!(serde_json::from_str::<Timestamp>("9223372036854775808").is_err());
+        assert!(serde_json::from_str::<Timestamp>(r#""9223372036854775808""#).is_err());
+
+        assert_eq!(serde_json::to_string(&timestamp).unwrap(), "1700000000");
+
+        // Numeric strings are rejected by the compact formats
+        let visitor = TimestampVisitor {
+            human_readable: false,
+        };
+        assert!(visitor
+            .visit_str::<serde::de::value::Error>("1700000000")
+            .is_err());
+    }
 }